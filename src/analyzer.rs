@@ -1,19 +1,820 @@
 mod analyze_rounding;
 pub mod ast;
+pub mod builtins;
+pub mod error_bound;
+pub mod evaluator;
+#[cfg(test)]
+pub(crate) mod fixtures;
+pub mod interval;
+pub mod lint;
+pub mod oracle;
+pub mod overflow;
+pub mod scale;
+pub mod search;
+pub mod verify;
+
+use std::collections::HashMap;
+use std::fmt::{Display, Error, Formatter};
+
+use anyhow::anyhow;
+use num_rational::BigRational;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 
 use crate::parser::arithmetic;
 use crate::FormulaConfig;
 
-use self::ast::Expr;
+use self::analyze_rounding::{Findings, Requirement};
+use self::ast::{bool_to_rounding, Expr, Rounding};
+use self::error_bound::ErrorBound;
+use self::evaluator::{Mode, Values};
+use self::lint::PrecisionLoss;
+use self::oracle::Oracle;
+use self::overflow::Magnitude;
+use self::scale::ScaleMismatch;
+use self::verify::Verification;
+
+/// The result of the analysis of a formula.
+pub struct Analysis {
+    /// The AST of the formula, annotated with the rounding directions.
+    pub ast: Box<Expr>,
+    /// The rounding direction the formula was analyzed with. It differs from `round_up`
+    /// when the formula is referenced by other formulas.
+    pub rounding: Rounding,
+    /// The `**` bases the oracle could not place relative to 1. The subtrees depending
+    /// on them are marked with `Rounding::Unknown`.
+    pub unresolved: Vec<String>,
+    /// Why the monotonicity of some `**` could not be determined from the declared
+    /// ranges. The operands depending on it are marked with `Rounding::Unknown`.
+    pub undetermined: Vec<String>,
+    /// The subtractions that can be negative, and revert, under the declared ranges.
+    pub underflows: Vec<String>,
+    /// The divisions whose results are multiplied, losing precision.
+    pub precision_losses: Vec<PrecisionLoss>,
+    /// The worst-case magnitude of each operation, if the config declares types.
+    pub magnitudes: Vec<Magnitude>,
+    /// The largest rounding error of each operation, if the config declares ranges.
+    pub error_bounds: Vec<ErrorBound>,
+    /// The largest rounding error of the result, if the config declares ranges.
+    pub total_error: Option<ErrorBound>,
+    /// The decimals of the result, if the config declares decimals and they are known.
+    pub decimals: Option<i64>,
+    /// The operands combined with different decimals, with the rounding of the division
+    /// rescaling them.
+    pub scale_mismatches: Vec<ScaleMismatch>,
+    /// The values required to round in opposite directions.
+    pub conflicts: Vec<Conflict>,
+    /// The rounding required for each identifier and numeric literal of the formula.
+    pub values: Vec<(String, ValueRounding)>,
+    /// The operations rounding in a fixed direction (annotated operations and library
+    /// calls), with the direction required.
+    pub checks: Vec<Check>,
+}
 
-pub fn analyze(formula_config: &mut FormulaConfig) -> anyhow::Result<Box<Expr>> {
+/// The rounding required for a value over all its occurrences in a formula.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueRounding {
+    /// All the occurrences agree on the rounding.
+    Required(Rounding),
+    /// The value is required to round up at one place and down at another.
+    Conflict,
+}
+
+impl Display for ValueRounding {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        match self {
+            ValueRounding::Required(rounding) => write!(fmt, "{rounding}"),
+            ValueRounding::Conflict => write!(fmt, "conflict"),
+        }
+    }
+}
+
+/// A value required to round in opposite directions: either an identifier used at
+/// several places of a formula, or the result of a formula used by other formulas.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Conflict {
+    /// The conflicting value.
+    pub value: String,
+    /// Each required direction, along with where it is required.
+    pub requirements: Vec<(String, Rounding)>,
+}
+
+impl Display for Conflict {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        let requirements: Vec<String> = self
+            .requirements
+            .iter()
+            .map(|(place, rounding)| format!("{rounding} ({place})"))
+            .collect();
+        write!(fmt, "{}: {}", self.value, requirements.join(", "))
+    }
+}
+
+/// An operation whose rounding direction is fixed by the formula: an annotated
+/// operation (`a *↓ b`) or a call to a library function (`mulWadDown(a, b)`).
+#[derive(Debug, PartialEq, Eq)]
+pub struct Check {
+    /// The operation, as written in the formula.
+    pub operation: String,
+    /// The direction the operation rounds to.
+    pub implemented: Rounding,
+    /// The direction required at the position of the operation.
+    pub required: Rounding,
+}
+
+/// Whether an operation rounds in the required direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// The operation rounds in the required direction.
+    Correct,
+    /// The operation rounds in the opposite direction.
+    WrongDirection,
+    /// Any direction is fine for the operation.
+    Unconstrained,
+    /// The required direction depends on unresolved assumptions or conflicting requirements.
+    Unknown,
+}
+
+impl Display for Verdict {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        match self {
+            Verdict::Correct => write!(fmt, "correct"),
+            Verdict::WrongDirection => write!(fmt, "wrong direction"),
+            Verdict::Unconstrained => write!(fmt, "unconstrained"),
+            Verdict::Unknown => write!(fmt, "unknown"),
+        }
+    }
+}
+
+impl Check {
+    /// Compares the implemented direction with the required one.
+    pub fn verdict(&self) -> Verdict {
+        match self.required {
+            Rounding::Init => Verdict::Unconstrained,
+            Rounding::Unknown => Verdict::Unknown,
+            required if required == self.implemented => Verdict::Correct,
+            _ => Verdict::WrongDirection,
+        }
+    }
+}
+
+impl Display for Check {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        write!(fmt, "{}: {}", self.operation, self.verdict())?;
+        match self.verdict() {
+            Verdict::WrongDirection => write!(
+                fmt,
+                " (rounds {}, {} is required)",
+                self.implemented, self.required
+            ),
+            _ => write!(fmt, " (rounds {})", self.implemented),
+        }
+    }
+}
+
+/// The value of a formula for concrete values of its identifiers.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Evaluation {
+    /// The exact rational value.
+    pub exact: BigRational,
+    /// The value with the operations rounding as the evaluation mode prescribes.
+    pub rounded: BigRational,
+}
+
+impl Evaluation {
+    /// How far the rounded value drifts from the exact value, positive if it is above.
+    pub fn error(&self) -> BigRational {
+        &self.rounded - &self.exact
+    }
+}
+
+/// Parses a formula into its AST.
+fn parse(formula: &str) -> anyhow::Result<Box<Expr>> {
     let parse_expression = arithmetic::ExprParser::new();
-    let formula = formula_config.formula.as_str();
-    let ast = parse_expression.parse(formula).map_err(|e| {
-        anyhow::anyhow!("Error occured while parsing the formula {}: {}", formula, e)
+    parse_expression
+        .parse(formula)
+        .map_err(|e| anyhow!("Error occured while parsing the formula {}: {}", formula, e))
+}
+
+/// Parses the formula of `formula_config` and analyzes its rounding.
+///
+/// The questions that the formula config cannot answer are forwarded to `oracle`,
+/// and its answers are recorded in `formula_config`.
+pub fn analyze(
+    formula_config: &mut FormulaConfig,
+    oracle: &mut dyn Oracle,
+) -> anyhow::Result<Analysis> {
+    let ast = parse(&formula_config.formula)?;
+    let rounding = bool_to_rounding(formula_config.round_up);
+    let (analysis, _) = run_passes(&ast, rounding, formula_config, oracle)?;
+    Ok(analysis)
+}
+
+/// Runs every pass of the analysis on a parsed formula, its result rounding in
+/// `rounding`. Also returns the directions required for its identifiers, through which
+/// `analyze_all` propagates the directions into the referenced formulas.
+///
+/// The passes reporting the operations as written (lints, magnitudes, error bounds,
+/// decimals) read the parsed AST, while the rounding analysis annotates a copy of it,
+/// the one kept in the `Analysis`.
+fn run_passes(
+    ast: &Expr,
+    rounding: Rounding,
+    formula_config: &mut FormulaConfig,
+    oracle: &mut dyn Oracle,
+) -> anyhow::Result<(Analysis, Vec<Requirement>)> {
+    let precision_losses = lint(ast, formula_config);
+    let magnitudes = magnitudes(ast, formula_config);
+    let (error_bounds, total_error) = error_bounds(ast, formula_config);
+    let decimals = decimals(ast, formula_config);
+    let annotated = Box::new(ast.clone());
+    let findings = analyze_rounding::analyze(&annotated, rounding, formula_config, oracle)?;
+
+    let analysis = Analysis {
+        ast: annotated,
+        rounding,
+        conflicts: identifier_conflicts(&findings.identifiers),
+        values: value_roundings(&findings),
+        checks: findings.checks,
+        undetermined: findings.undetermined,
+        underflows: findings.underflows,
+        precision_losses,
+        magnitudes,
+        error_bounds,
+        total_error,
+        decimals,
+        scale_mismatches: findings.scale_mismatches,
+        unresolved: findings.unresolved,
+    };
+    Ok((analysis, findings.identifiers))
+}
+
+/// Runs the lints, with the declared ranges if any.
+fn lint(ast: &Expr, formula_config: &FormulaConfig) -> Vec<PrecisionLoss> {
+    let no_ranges = interval::Ranges::new();
+    let ranges = formula_config.ranges.as_ref().unwrap_or(&no_ranges);
+    lint::divide_before_multiply(ast, ranges)
+}
+
+/// Computes the magnitudes of the operations, if the config declares types.
+fn magnitudes(ast: &Expr, formula_config: &FormulaConfig) -> Vec<Magnitude> {
+    let Some(types) = &formula_config.types else {
+        return vec![];
+    };
+    let no_ranges = interval::Ranges::new();
+    let ranges = formula_config.ranges.as_ref().unwrap_or(&no_ranges);
+    overflow::magnitudes(ast, types, ranges)
+}
+
+/// Computes the rounding error bounds of the operations and of the result, if the
+/// config declares ranges.
+fn error_bounds(
+    ast: &Expr,
+    formula_config: &FormulaConfig,
+) -> (Vec<ErrorBound>, Option<ErrorBound>) {
+    match &formula_config.ranges {
+        Some(ranges) => {
+            let (bounds, total) = error_bound::error_bounds(ast, ranges);
+            (bounds, Some(total))
+        }
+        None => (vec![], None),
+    }
+}
+
+/// Computes the decimals of the result, if the config declares decimals.
+fn decimals(ast: &Expr, formula_config: &FormulaConfig) -> Option<i64> {
+    scale::eval(ast, formula_config.decimals.as_ref()?)
+}
+
+/// The parsed formulas of a config file.
+struct Formulas {
+    /// The AST of each formula.
+    asts: Vec<Expr>,
+    /// The index of each named formula.
+    names: HashMap<String, usize>,
+    /// The indices of the formulas, each formula coming before the formulas it references.
+    order: Vec<usize>,
+}
+
+/// Parses the formulas of a config file, see `Formulas`. `action` and its past
+/// `participle`, e.g. `"search"` and `"searched"`, name what the formulas are parsed for
+/// in the errors.
+fn parse_all(
+    formula_configs: &[FormulaConfig],
+    action: &str,
+    participle: &str,
+) -> anyhow::Result<Formulas> {
+    let asts = formula_configs
+        .iter()
+        .enumerate()
+        .map(|(index, formula_config)| {
+            parse(&formula_config.formula).map(|ast| *ast).map_err(|e| {
+                anyhow!(
+                    "Failed to {} {}: {}",
+                    action,
+                    formula_config.label(index),
+                    e
+                )
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let names: HashMap<String, usize> = formula_configs
+        .iter()
+        .enumerate()
+        .filter_map(|(index, config)| config.name.clone().map(|name| (name, index)))
+        .collect();
+
+    let references: Vec<Vec<usize>> = asts
+        .iter()
+        .map(|ast| {
+            let mut ids = vec![];
+            collect_ids(ast, &mut ids);
+            ids.iter().filter_map(|id| names.get(id).copied()).collect()
+        })
+        .collect();
+
+    let order = consumers_first(&references).ok_or_else(|| {
+        anyhow!("The formulas reference each other in a cycle, they can not be {participle}")
     })?;
 
-    analyze_rounding::analyze(&ast, formula_config.round_up, formula_config)?;
+    Ok(Formulas { asts, names, order })
+}
+
+/// Analyzes every formula of a config file, see `analyze`.
+///
+/// A formula can use the result of another named formula through its name. The
+/// direction required where the name is used is then propagated into the referenced
+/// formula, along with its own `round_up`. If these directions disagree, the conflict
+/// is reported and the referenced formula is analyzed with `Rounding::Unknown`.
+pub fn analyze_all(
+    formula_configs: &mut [FormulaConfig],
+    oracle: &mut dyn Oracle,
+) -> anyhow::Result<Vec<Analysis>> {
+    let Formulas { asts, names, order } = parse_all(formula_configs, "analyze", "analyzed")?;
+
+    // Directions required for each formula, with where they are required
+    let mut requirements: Vec<Vec<(String, Rounding)>> = formula_configs
+        .iter()
+        .map(|config| vec![(String::from("round_up"), bool_to_rounding(config.round_up))])
+        .collect();
+
+    let mut analyses: Vec<Option<Analysis>> = formula_configs.iter().map(|_| None).collect();
+
+    for index in order {
+        let label = formula_configs[index].label(index);
+        let (rounding, mut conflicts) = required_rounding(&label, &requirements[index]);
+
+        let (mut analysis, identifiers) =
+            run_passes(&asts[index], rounding, &mut formula_configs[index], oracle)
+                .map_err(|e| anyhow!("Failed to analyze {}: {}", label, e))?;
+
+        for requirement in &identifiers {
+            if let Some(&referenced) = names.get(&requirement.value) {
+                requirements[referenced].push((label.clone(), requirement.rounding));
+            }
+        }
+
+        conflicts.append(&mut analysis.conflicts);
+        analysis.conflicts = conflicts;
+        analyses[index] = Some(analysis);
+    }
+
+    Ok(analyses.into_iter().map(Option::unwrap).collect())
+}
+
+/// Evaluates every formula of a config file, exactly and with the operations rounding
+/// as `mode` prescribes, see `evaluator::eval`.
+///
+/// A formula using the result of another named formula through its name is evaluated
+/// with the value of that formula, exact or rounded respectively.
+pub fn evaluate_all(
+    formula_configs: &[FormulaConfig],
+    values: &Values,
+    mode: Mode,
+) -> anyhow::Result<Vec<Evaluation>> {
+    let Formulas { asts, order, .. } = parse_all(formula_configs, "evaluate", "evaluated")?;
+
+    let mut exact_values = values.clone();
+    let mut rounded_values = values.clone();
+    let mut evaluations: Vec<Option<Evaluation>> = formula_configs.iter().map(|_| None).collect();
+
+    // The referenced formulas are evaluated before their consumers
+    for index in order.into_iter().rev() {
+        let label = formula_configs[index].label(index);
+        let evaluate = |values: &Values, mode: Mode| {
+            evaluator::eval(&asts[index], values, mode)
+                .map_err(|e| anyhow!("Failed to evaluate {}: {}", label, e))
+        };
+        let evaluation = Evaluation {
+            exact: evaluate(&exact_values, Mode::Exact)?,
+            rounded: evaluate(&rounded_values, mode)?,
+        };
+
+        if let Some(name) = &formula_configs[index].name {
+            exact_values.insert(name.clone(), evaluation.exact.clone());
+            rounded_values.insert(name.clone(), evaluation.rounded.clone());
+        }
+        evaluations[index] = Some(evaluation);
+    }
+
+    Ok(evaluations.into_iter().map(Option::unwrap).collect())
+}
+
+/// Checks the directions recommended by the analyses on `samples` random inputs per
+/// formula drawn in the declared ranges, see `verify::verify`. The inputs are drawn
+/// from `seed`, so a check can be reproduced.
+///
+/// The names of the referenced formulas are drawn as any other identifier.
+pub fn verify_all(
+    analyses: &[Analysis],
+    formula_configs: &[FormulaConfig],
+    samples: usize,
+    seed: u64,
+) -> Vec<Verification> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let no_ranges = interval::Ranges::new();
+    analyses
+        .iter()
+        .zip(formula_configs)
+        .map(|(analysis, formula_config)| {
+            let ranges = formula_config.ranges.as_ref().unwrap_or(&no_ranges);
+            verify::verify(&analysis.ast, analysis.rounding, ranges, samples, &mut rng)
+        })
+        .collect()
+}
+
+/// Searches inputs for which the formulas, as implemented, round on the wrong side of
+/// their `round_up`, see `search::search`. The random combinations are drawn from
+/// `seed`, so a search can be reproduced.
+///
+/// The names of the referenced formulas are tried as any other identifier.
+pub fn search_all(
+    formula_configs: &[FormulaConfig],
+    budget: usize,
+    seed: u64,
+) -> anyhow::Result<Vec<Verification>> {
+    let Formulas { asts, .. } = parse_all(formula_configs, "search", "searched")?;
+    let mut rng = StdRng::seed_from_u64(seed);
+    let no_ranges = interval::Ranges::new();
+    Ok(asts
+        .iter()
+        .zip(formula_configs)
+        .map(|(ast, formula_config)| {
+            let ranges = formula_config.ranges.as_ref().unwrap_or(&no_ranges);
+            let rounding = bool_to_rounding(formula_config.round_up);
+            search::search(ast, rounding, ranges, budget, &mut rng)
+        })
+        .collect())
+}
+
+/// Merges the directions required for the result of a formula.
+///
+/// Returns `Rounding::Unknown` along with the conflict if they disagree.
+fn required_rounding(
+    label: &str,
+    requirements: &[(String, Rounding)],
+) -> (Rounding, Vec<Conflict>) {
+    let required = |direction| requirements.iter().any(|(_, r)| *r == direction);
+
+    if required(Rounding::Unknown) {
+        return (Rounding::Unknown, vec![]);
+    }
+
+    if required(Rounding::Up) && required(Rounding::Down) {
+        let conflict = Conflict {
+            value: label.to_string(),
+            requirements: requirements.to_vec(),
+        };
+        return (Rounding::Unknown, vec![conflict]);
+    }
+
+    let rounding = requirements
+        .iter()
+        .map(|(_, r)| *r)
+        .find(|r| *r != Rounding::Init)
+        .unwrap_or(Rounding::Init);
+    (rounding, vec![])
+}
+
+/// Finds the identifiers required to round up at one place and down at another.
+///
+/// A single precomputed value cannot satisfy both uses, the formula needs to compute
+/// the identifier twice with different roundings.
+fn identifier_conflicts(identifiers: &[Requirement]) -> Vec<Conflict> {
+    let mut names: Vec<&str> = identifiers.iter().map(|r| r.value.as_str()).collect();
+    names.sort_unstable();
+    names.dedup();
+
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let uses: Vec<&Requirement> = identifiers.iter().filter(|r| r.value == name).collect();
+            let required = |direction| uses.iter().any(|r| r.rounding == direction);
+            (required(Rounding::Up) && required(Rounding::Down)).then(|| Conflict {
+                value: name.to_string(),
+                requirements: uses
+                    .iter()
+                    .map(|r| (r.location.clone(), r.rounding))
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+/// Merges the rounding required for each occurrence of the identifiers and literals,
+/// in order of first occurrence.
+fn value_roundings(findings: &Findings) -> Vec<(String, ValueRounding)> {
+    let requirements: Vec<&Requirement> = findings
+        .identifiers
+        .iter()
+        .chain(&findings.literals)
+        .collect();
+
+    let mut values: Vec<&str> = vec![];
+    for requirement in &requirements {
+        if !values.contains(&requirement.value.as_str()) {
+            values.push(&requirement.value);
+        }
+    }
+
+    values
+        .into_iter()
+        .map(|value| {
+            let required = |direction| {
+                requirements
+                    .iter()
+                    .any(|r| r.value == value && r.rounding == direction)
+            };
+            let rounding = if required(Rounding::Up) && required(Rounding::Down) {
+                ValueRounding::Conflict
+            } else if required(Rounding::Unknown) {
+                ValueRounding::Required(Rounding::Unknown)
+            } else if required(Rounding::Up) {
+                ValueRounding::Required(Rounding::Up)
+            } else if required(Rounding::Down) {
+                ValueRounding::Required(Rounding::Down)
+            } else {
+                ValueRounding::Required(Rounding::Init)
+            };
+            (value.to_string(), rounding)
+        })
+        .collect()
+}
+
+/// Sorts the formulas so that each formula comes before the formulas it references.
+///
+/// Returns `None` if the references contain a cycle.
+fn consumers_first(references: &[Vec<usize>]) -> Option<Vec<usize>> {
+    let mut consumers = vec![0; references.len()];
+    for referenced in references.iter().flatten() {
+        consumers[*referenced] += 1;
+    }
+
+    let mut ready: Vec<usize> = (0..references.len())
+        .rev()
+        .filter(|index| consumers[*index] == 0)
+        .collect();
+    let mut order = vec![];
+
+    while let Some(index) = ready.pop() {
+        order.push(index);
+        for referenced in &references[index] {
+            consumers[*referenced] -= 1;
+            if consumers[*referenced] == 0 {
+                ready.push(*referenced);
+            }
+        }
+    }
+
+    (order.len() == references.len()).then_some(order)
+}
+
+/// Collects the identifiers used in an expression.
+pub(crate) fn collect_ids(expr: &Expr, ids: &mut Vec<String>) {
+    match expr {
+        Expr::Id(name) => ids.push(name.clone()),
+        Expr::Op(left, _, right) => {
+            collect_ids(left, ids);
+            collect_ids(right, ids);
+        }
+        Expr::Neg(expr) => collect_ids(expr, ids),
+        Expr::Cond(condition, then, otherwise) => {
+            collect_ids(&condition.left, ids);
+            collect_ids(&condition.right, ids);
+            collect_ids(then, ids);
+            collect_ids(otherwise, ids);
+        }
+        Expr::Call(_, args, _) => {
+            for arg in args {
+                collect_ids(arg, ids);
+            }
+        }
+        Expr::Number(_) | Expr::Error => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::fixtures::{self, named_config};
+    use crate::analyzer::oracle::ConfigOracle;
+
+    #[test]
+    fn test_analyze_all_references() {
+        let mut configs = vec![
+            named_config("invariant", "a * b", false),
+            named_config("out", "c / invariant", true),
+        ];
+        let analyses = analyze_all(&mut configs, &mut ConfigOracle).unwrap();
+        assert_eq!(format!("{}", analyses[0].ast), "(a *↓ b)");
+        assert_eq!(format!("{}", analyses[1].ast), "(c /↑ invariant)");
+        assert!(analyses[0].conflicts.is_empty());
+    }
+
+    #[test]
+    fn test_analyze_all_conflicting_references() {
+        let mut configs = vec![
+            named_config("invariant", "a * b", false),
+            named_config("out", "c / invariant", true),
+            named_config("in", "invariant * d", true),
+        ];
+        let analyses = analyze_all(&mut configs, &mut ConfigOracle).unwrap();
+        assert_eq!(format!("{}", analyses[0].ast), "(a *↕ b)");
+        assert_eq!(analyses[0].rounding, Rounding::Unknown);
+        assert_eq!(
+            analyses[0].conflicts,
+            vec![Conflict {
+                value: "invariant".to_string(),
+                requirements: vec![
+                    ("round_up".to_string(), Rounding::Down),
+                    ("out".to_string(), Rounding::Down),
+                    ("in".to_string(), Rounding::Up),
+                ],
+            }]
+        );
+        assert_eq!(
+            format!("{}", analyses[0].conflicts[0]),
+            "invariant: down (round_up), down (out), up (in)"
+        );
+    }
+
+    #[test]
+    fn test_analyze_identifier_conflicts() {
+        let mut formula_config = named_config("out", "b * (1 - b / c) + c", false);
+        let analysis = analyze(&mut formula_config, &mut ConfigOracle).unwrap();
+        assert_eq!(
+            analysis.conflicts,
+            vec![Conflict {
+                value: "b".to_string(),
+                requirements: vec![
+                    ("b * (1 - (b / c))".to_string(), Rounding::Down),
+                    ("b / c".to_string(), Rounding::Up),
+                ],
+            }]
+        );
+        assert_eq!(
+            format!("{}", analysis.conflicts[0]),
+            "b: down (b * (1 - (b / c))), up (b / c)"
+        );
+        assert_eq!(
+            analysis.values,
+            vec![
+                ("b".to_string(), ValueRounding::Conflict),
+                ("c".to_string(), ValueRounding::Required(Rounding::Down)),
+                ("1".to_string(), ValueRounding::Required(Rounding::Down)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_analyze_checks() {
+        let mut formula_config =
+            named_config("out", "mulWadDown(a, b) - FullMath.mulDiv(c, d, e)", true);
+        let analysis = analyze(&mut formula_config, &mut ConfigOracle).unwrap();
+        let checks: Vec<String> = analysis.checks.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            checks,
+            vec![
+                "mulWadDown(a, b): wrong direction (rounds down, up is required)".to_string(),
+                "FullMath.mulDiv(c, d, e): correct (rounds down)".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_analyze_annotated() {
+        let mut formula_config = named_config("out", "a *↓ b /↑ c + (d /↓ e) ** 0", true);
+        formula_config.add_greater_than_one("(d /↓ e)".to_string());
+        let analysis = analyze(&mut formula_config, &mut ConfigOracle).unwrap();
+        assert_eq!(
+            format!("{}", analysis.ast),
+            "(((a *↑ b) /↑ c) + ((d /↑ e) ** 0))"
+        );
+        let verdicts: Vec<(&str, Verdict)> = analysis
+            .checks
+            .iter()
+            .map(|check| (check.operation.as_str(), check.verdict()))
+            .collect();
+        assert_eq!(
+            verdicts,
+            vec![
+                ("(a *↓ b) /↑ c", Verdict::Correct),
+                ("a *↓ b", Verdict::WrongDirection),
+                ("d /↓ e", Verdict::WrongDirection),
+            ]
+        );
+
+        let mut formula_config = named_config("out", "c ** (a *↓ b)", true);
+        formula_config.add_greater_than_one("c".to_string());
+        let analysis = analyze(&mut formula_config, &mut ConfigOracle).unwrap();
+        assert_eq!(analysis.checks[0].verdict(), Verdict::WrongDirection);
+    }
+
+    #[test]
+    fn test_verdict() {
+        let check = |implemented, required| Check {
+            operation: "a * b".to_string(),
+            implemented,
+            required,
+        };
+        assert_eq!(
+            check(Rounding::Up, Rounding::Up).verdict(),
+            Verdict::Correct
+        );
+        assert_eq!(
+            check(Rounding::Up, Rounding::Down).verdict(),
+            Verdict::WrongDirection
+        );
+        assert_eq!(
+            check(Rounding::Down, Rounding::Init).verdict(),
+            Verdict::Unconstrained
+        );
+        assert_eq!(
+            check(Rounding::Down, Rounding::Unknown).verdict(),
+            Verdict::Unknown
+        );
+        assert_eq!(
+            check(Rounding::Down, Rounding::Init).to_string(),
+            "a * b: unconstrained (rounds down)"
+        );
+    }
+
+    #[test]
+    fn test_evaluate_all() {
+        let configs = vec![
+            named_config("amountOut", "invariant / c", true),
+            named_config("invariant", "a * b / 3", false),
+        ];
+        let values: Values = ["a=2", "b=5", "c=2"]
+            .iter()
+            .map(|assignment| evaluator::parse_value(assignment).unwrap())
+            .collect();
+        let evaluations = evaluate_all(&configs, &values, Mode::Floor).unwrap();
+        let ratio = |numer: i64, denom: i64| BigRational::new(numer.into(), denom.into());
+        assert_eq!(evaluations[0].exact, ratio(5, 3));
+        assert_eq!(evaluations[0].rounded, ratio(1, 1));
+        assert_eq!(evaluations[0].error(), ratio(-2, 3));
+        assert_eq!(evaluations[1].rounded, ratio(3, 1));
+
+        let values: Values = [("a".to_string(), ratio(1, 1))].into();
+        assert!(evaluate_all(&configs, &values, Mode::Floor).is_err());
+    }
+
+    #[test]
+    fn test_verify_all() {
+        let mut configs = vec![named_config("price", "a * b / (c - a)", true)];
+        configs[0].ranges = Some(fixtures::ranges(&[
+            ("a", "[0, 1e18]"),
+            ("c", "[2e18, 1e30]"),
+        ]));
+        let analyses = analyze_all(&mut configs, &mut ConfigOracle).unwrap();
+        let verifications = verify_all(&analyses, &configs, 200, 0);
+        assert_eq!(verifications[0].samples, 200);
+        assert!(verifications[0].counterexample.is_none());
+    }
+
+    #[test]
+    fn test_search_all() {
+        let configs = vec![
+            named_config("down", "mulDivDown(a, b, 3)", true),
+            named_config("up", "a *↑ b /↑ 3", true),
+        ];
+        let searches = search_all(&configs, 1000, 0).unwrap();
+        assert!(searches[0].counterexample.is_some());
+        assert!(searches[1].counterexample.is_none());
+    }
 
-    Ok(ast)
+    #[test]
+    fn test_analyze_all_cycle() {
+        let mut configs = vec![
+            named_config("a", "b + 1", true),
+            named_config("b", "a + 1", true),
+        ];
+        assert!(analyze_all(&mut configs, &mut ConfigOracle).is_err());
+        assert_eq!(
+            search_all(&configs, 1000, 0).unwrap_err().to_string(),
+            "The formulas reference each other in a cycle, they can not be searched"
+        );
+    }
 }
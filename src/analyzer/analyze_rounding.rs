@@ -1,67 +1,431 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use num_rational::BigRational;
+use num_traits::Zero;
 use std::cell::RefCell;
+use std::str::FromStr;
 
-use super::ast::bool_to_rounding;
 use super::ast::Expr;
+use super::ast::Literal;
 use super::ast::Opcode;
 use super::ast::Rounding;
-use crate::parser::ask_yes_no;
+use super::builtins::{self, Semantics};
+use super::collect_ids;
+use super::interval::{self, Ranges};
+use super::oracle::Oracle;
+use super::scale::{self, ScaleMismatch};
+use super::Check;
 use crate::FormulaConfig;
 
+/// What the analysis learned besides the rounding of the operations.
+#[derive(Debug, Default)]
+pub struct Findings {
+    /// Bases of `**` that neither the config nor the oracle could place relative to 1.
+    pub unresolved: Vec<String>,
+    /// Why the monotonicity of some `**` could not be determined from the ranges.
+    pub undetermined: Vec<String>,
+    /// The subtractions that can be negative under the declared ranges.
+    pub underflows: Vec<String>,
+    /// The operands combined with different decimals.
+    pub scale_mismatches: Vec<ScaleMismatch>,
+    /// The rounding direction required for each occurrence of an identifier.
+    pub identifiers: Vec<Requirement>,
+    /// The rounding direction required for each occurrence of a numeric literal.
+    pub literals: Vec<Requirement>,
+    /// The operations rounding in a fixed direction, with the direction required.
+    pub checks: Vec<Check>,
+}
+
+/// The rounding direction required for an occurrence of a value.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Requirement {
+    /// The value, as displayed in the formula.
+    pub value: String,
+    /// The required rounding direction.
+    pub rounding: Rounding,
+    /// The operation using the value.
+    pub location: String,
+}
+
+/// State shared while visiting the expression.
+struct Context<'a> {
+    formula_config: &'a mut FormulaConfig,
+    oracle: &'a mut dyn Oracle,
+    findings: Findings,
+    /// The operation the visited expression is an operand of.
+    location: String,
+}
+
+/// Annotates the operation with the required direction. If the formula already annotated
+/// it with the implemented direction, the two are checked against each other.
+fn annotate(rounding_direction: Rounding, op_rounding: &RefCell<Rounding>, context: &mut Context) {
+    let implemented = op_rounding.replace(rounding_direction);
+    if matches!(implemented, Rounding::Up | Rounding::Down) {
+        context.findings.checks.push(Check {
+            operation: context.location.clone(),
+            implemented,
+            required: rounding_direction,
+        });
+    }
+}
+
+// Sub
+// Up -> A - B -> A up, B down
+// Down -> A - B -> A down, B up
+// The subtraction reverts if the result is negative, which is checked with the ranges
+fn handle_sub(
+    left: &Expr,
+    right: &Expr,
+    rounding_direction: Rounding,
+    context: &mut Context,
+) -> (Rounding, Rounding) {
+    check_underflow(left, right, rounding_direction, context);
+    (rounding_direction, !rounding_direction)
+}
+
+/// Reports the subtraction if it can be negative under the declared ranges, either
+/// exactly or because of the rounding of its operands. A subtraction that can be 0 is
+/// only reported if an operand rounds, the identifiers and literals being exact.
+///
+/// Only the subtractions whose identifiers all have a declared range are checked.
+fn check_underflow(left: &Expr, right: &Expr, rounding_direction: Rounding, context: &mut Context) {
+    let Some(ranges) = &context.formula_config.ranges else {
+        return;
+    };
+    let mut ids = vec![];
+    collect_ids(left, &mut ids);
+    collect_ids(right, &mut ids);
+    if !ids.iter().all(|id| ranges.contains_key(id)) {
+        return;
+    }
+
+    let difference = interval::eval(left, ranges).sub(&interval::eval(right, ranges));
+    let zero = BigRational::zero();
+    let underflow = match &difference.low {
+        Some(low) if low.value < zero => format!(
+            "{} can underflow, it ranges over {difference}",
+            context.location
+        ),
+        // Rounding the left operand down or the right operand up goes below zero
+        Some(low)
+            if low.value == zero
+                && matches!(rounding_direction, Rounding::Down | Rounding::Unknown)
+                && (rounds(left) || rounds(right)) =>
+        {
+            format!(
+                "{} can underflow, it can be 0 and {left} rounds {rounding_direction} while {right} rounds {}",
+                context.location, !rounding_direction
+            )
+        }
+        Some(_) => return,
+        None => format!("{} can underflow, it is unbounded below", context.location),
+    };
+    context.findings.underflows.push(underflow);
+}
+
+/// Whether an expression contains an operation that rounds: a multiplication, a
+/// division, a `**` or a library call.
+fn rounds(expr: &Expr) -> bool {
+    match expr {
+        Expr::Op(_, Opcode::Mul(_) | Opcode::Div(_) | Opcode::Pow, _) | Expr::Call(..) => true,
+        Expr::Op(left, _, right) => rounds(left) || rounds(right),
+        Expr::Neg(expr) => rounds(expr),
+        Expr::Cond(_, then, otherwise) => rounds(then) || rounds(otherwise),
+        Expr::Number(_) | Expr::Id(_) | Expr::Error => false,
+    }
+}
+
+/// Reports the operands of the current operation whose decimals differ, if the config
+/// declares decimals.
+fn check_scales(left: (&Expr, Rounding), right: (&Expr, Rounding), context: &mut Context) {
+    let Some(decimals) = &context.formula_config.decimals else {
+        return;
+    };
+    if let Some(mismatch) = scale::mismatch(&context.location, left, right, decimals) {
+        context.findings.scale_mismatches.push(mismatch);
+    }
+}
+
 // Mulplication
 // Up -> A * B -> A up, B up, * up
 // Down  -> A * B -> A down, B down, * down
-fn handle_mul(rounding_direction: bool, op_rounding: &RefCell<Rounding>) -> (bool, bool) {
-    *op_rounding.borrow_mut() = bool_to_rounding(rounding_direction);
+fn handle_mul(
+    rounding_direction: Rounding,
+    op_rounding: &RefCell<Rounding>,
+    context: &mut Context,
+) -> (Rounding, Rounding) {
+    annotate(rounding_direction, op_rounding, context);
     (rounding_direction, rounding_direction)
 }
 
 // Div
 // Up -> A / B -> A up, B down, / up
 // Down -> A / B -> A down, B up, / down
-fn handle_div(rounding_direction: bool, op_rounding: &RefCell<Rounding>) -> (bool, bool) {
-    *op_rounding.borrow_mut() = bool_to_rounding(rounding_direction);
+fn handle_div(
+    rounding_direction: Rounding,
+    op_rounding: &RefCell<Rounding>,
+    context: &mut Context,
+) -> (Rounding, Rounding) {
+    annotate(rounding_direction, op_rounding, context);
     (rounding_direction, !rounding_direction)
 }
 
 // Pow
-// a ** B
-// Up: if A >=1  -> A up, b up
-// Up: if A <1  -> A up, b down
-// Down: if A >=1  -> A down, b down
-// Down: if A <1  -> A down, b up
+// A ** B is increasing in A if B >= 0 and decreasing if B <= 0, it is increasing in B
+// if A >= 1 and decreasing if A <= 1
+// Up: if A >= 1, B >= 0 -> A up, B up
+// Up: if A < 1, B >= 0 -> A up, B down
+// Up: if A >= 1, B <= 0 -> A down, B up
+// Up: if A < 1, B <= 0 -> A down, B down
+// Down: the opposite
+// Unknown -> A unknown, B unknown
+// Undetermined -> the operand depending on it is unknown
+// The fixed-point powers compare A with their scalar instead of 1, e.g. 1e18 for powDown
 fn handle_pow(
     left: &Expr,
-    rounding_direction: bool,
-    formula_config: &mut FormulaConfig,
-) -> Result<(bool, bool)> {
+    right: &Expr,
+    one: &Expr,
+    rounding_direction: Rounding,
+    context: &mut Context,
+) -> Result<(Rounding, Rounding)> {
+    // Nothing to ask if any direction is fine, or if the direction is already unknown
+    if let Rounding::Init | Rounding::Unknown = rounding_direction {
+        return Ok((rounding_direction, rounding_direction));
+    }
+
+    let left_rounding = match exponent_sign(left, right, context) {
+        Some(true) => rounding_direction,
+        Some(false) => !rounding_direction,
+        None => Rounding::Unknown,
+    };
+    let right_rounding = match base_compared_to_one(left, one, context)? {
+        Some(true) => rounding_direction,
+        Some(false) => !rounding_direction,
+        None => Rounding::Unknown,
+    };
+    Ok((left_rounding, right_rounding))
+}
+
+/// Whether an expression negates, subtracts or takes a logarithm, the ways its
+/// identifiers, taken as non-negative, can make it negative.
+fn can_be_negative(expr: &Expr) -> bool {
+    match expr {
+        Expr::Neg(_) | Expr::Op(_, Opcode::Sub, _) => true,
+        Expr::Op(left, _, right) => can_be_negative(left) || can_be_negative(right),
+        Expr::Call(name, args, _) => {
+            builtins::lookup(name).is_some_and(|builtin| {
+                matches!(
+                    builtin.semantics,
+                    Semantics::Ln | Semantics::LnWad | Semantics::Log2
+                )
+            }) || args.iter().any(can_be_negative)
+        }
+        Expr::Cond(_, then, otherwise) => can_be_negative(then) || can_be_negative(otherwise),
+        Expr::Number(_) | Expr::Id(_) | Expr::Error => false,
+    }
+}
+
+/// Returns whether the exponent is non-negative (`Some(true)`) or non-positive
+/// (`Some(false)`), from the declared ranges.
+///
+/// An exponent without any declared range is assumed to be non-negative, as the
+/// exponents of the fixed-point formulas mostly are, unless it can be negative
+/// (`can_be_negative`).
+fn exponent_sign(left: &Expr, right: &Expr, context: &mut Context) -> Option<bool> {
+    let no_ranges = Ranges::new();
+    let ranges = context.formula_config.ranges.as_ref().unwrap_or(&no_ranges);
+    let exponent = interval::eval(right, ranges);
+
+    if exponent.is_non_negative() {
+        return Some(true);
+    }
+    if exponent.is_at_most(&BigRational::zero()) {
+        return Some(false);
+    }
+
+    let mut ids = vec![];
+    collect_ids(right, &mut ids);
+    if !ids.iter().any(|id| ranges.contains_key(id)) {
+        if !can_be_negative(right) {
+            return Some(true);
+        }
+        context.findings.undetermined.push(format!(
+            "The exponent {right} can be negative without declared ranges, the rounding of the base {left} is unknown"
+        ));
+        return None;
+    }
+
+    context.findings.undetermined.push(format!(
+        "The exponent {right} ranges over {exponent}, the rounding of the base {left} is unknown"
+    ));
+    None
+}
+
+/// Places the base relative to `one`, the value standing for 1 (`1e18` for a WAD), with
+/// the declared ranges first, then with the less_than_one and greater_than_one lists,
+/// and last with the oracle. The lists and the oracle place the value the base stands
+/// for.
+///
+/// The ranges only conclude that the base straddles `one` if all the identifiers of the
+/// base and of `one` have a declared range, as `check_underflow` does.
+///
+/// Returns `Some(true)` if the base is at least `one`, `Some(false)` if it is at most
+/// `one`.
+fn base_compared_to_one(left: &Expr, one: &Expr, context: &mut Context) -> Result<Option<bool>> {
     let expr_str = format!("{left}");
 
-    if formula_config
+    let mut straddling = None;
+    if let Some(ranges) = &context.formula_config.ranges {
+        let base = interval::eval(left, ranges);
+        match base.compare_to(&interval::eval(one, ranges)) {
+            Some(greater) => return Ok(Some(greater)),
+            None => {
+                let mut ids = vec![];
+                collect_ids(left, &mut ids);
+                collect_ids(one, &mut ids);
+                if ids.iter().all(|id| ranges.contains_key(id)) {
+                    straddling = Some(base);
+                }
+            }
+        }
+    }
+
+    if context
+        .formula_config
         .less_than_one
         .as_ref()
-        .map_or(false, |vec| vec.contains(&expr_str))
+        .is_some_and(|vec| vec.contains(&expr_str))
     {
-        return Ok((rounding_direction, !rounding_direction));
+        return Ok(Some(false));
     }
 
-    if formula_config
+    if context
+        .formula_config
         .greater_than_one
         .as_ref()
-        .map_or(false, |vec| vec.contains(&expr_str))
+        .is_some_and(|vec| vec.contains(&expr_str))
     {
-        return Ok((rounding_direction, rounding_direction));
+        return Ok(Some(true));
     }
 
-    println!("Is {} greater than 1? Y/N (yes, no)", &left);
+    // The declared ranges tell that the base crosses 1, there is nothing to ask
+    if let Some(base) = straddling {
+        context.findings.undetermined.push(format!(
+            "The base {expr_str} ranges over {base}, across {one}, the rounding of its exponent is unknown"
+        ));
+        return Ok(None);
+    }
 
-    if ask_yes_no()? {
-        formula_config.add_greater_than_one(expr_str);
-        Ok((rounding_direction, rounding_direction))
+    match context.oracle.is_greater_than_one(left)? {
+        Some(true) => {
+            context.formula_config.add_greater_than_one(expr_str);
+            Ok(Some(true))
+        }
+        Some(false) => {
+            context.formula_config.add_less_than_one(expr_str);
+            Ok(Some(false))
+        }
+        None => {
+            context.findings.unresolved.push(expr_str);
+            Ok(None)
+        }
+    }
+}
+
+// Increasing functions: sqrt, ln, exp, log2, and min/max in each argument
+// Up -> f(A) -> A up
+// Down -> f(A) -> A down
+
+// Abs
+// Up: if A >= 0 -> A up
+// Up: if A < 0 -> A down
+// Down: if A >= 0 -> A down
+// Down: if A < 0 -> A up
+// The sign of A comes from the declared ranges, A is unknown if it crosses 0
+fn handle_abs(arg: &Expr, rounding_direction: Rounding, context: &mut Context) -> Rounding {
+    // Nothing to place if any direction is fine
+    if rounding_direction == Rounding::Init {
+        return Rounding::Init;
+    }
+
+    let no_ranges = Ranges::new();
+    let ranges = context.formula_config.ranges.as_ref().unwrap_or(&no_ranges);
+    let interval = interval::eval(arg, ranges);
+    if interval.is_non_negative() {
+        rounding_direction
+    } else if interval.is_at_most(&BigRational::zero()) {
+        !rounding_direction
     } else {
-        formula_config.add_less_than_one(expr_str);
-        Ok((rounding_direction, !rounding_direction))
+        context.findings.undetermined.push(format!(
+            "The argument {arg} of abs ranges over {interval}, across 0, its rounding is unknown"
+        ));
+        Rounding::Unknown
+    }
+}
+
+// Call
+// The arguments follow the semantics of the function, e.g. mulDiv(A, B, C) is A * B / C:
+// Up -> A up, B up, C down
+// Down -> A down, B down, C up
+// The direction implemented by the function is checked against the required one
+fn handle_call(
+    name: &str,
+    args: &[Expr],
+    rounding_direction: Rounding,
+    call_rounding: &RefCell<Rounding>,
+    context: &mut Context,
+) -> Result<Vec<Rounding>> {
+    let builtin = builtins::lookup(name).ok_or_else(|| anyhow!("Unknown function {name}"))?;
+
+    if let Some(implemented) = builtin.rounding {
+        context.findings.checks.push(Check {
+            operation: context.location.clone(),
+            implemented,
+            required: rounding_direction,
+        });
     }
+    *call_rounding.borrow_mut() = rounding_direction;
+
+    let mut directions = match builtin.semantics {
+        Semantics::MulDiv => vec![rounding_direction, rounding_direction, !rounding_direction],
+        Semantics::MulWad => vec![rounding_direction, rounding_direction],
+        Semantics::DivWad | Semantics::Div => vec![rounding_direction, !rounding_direction],
+        Semantics::Pow | Semantics::PowWad | Semantics::RPow => {
+            // The value standing for 1: 1e18 for the WADs, the scalar of rpow
+            let unit = if builtin.semantics == Semantics::PowWad {
+                "1e18"
+            } else {
+                "1"
+            };
+            let unit = Expr::Number(Literal::from_str(unit).unwrap());
+            let one = if builtin.semantics == Semantics::RPow {
+                &args[2]
+            } else {
+                &unit
+            };
+            let (base, exponent) =
+                handle_pow(&args[0], &args[1], one, rounding_direction, context)?;
+            vec![base, exponent]
+        }
+        Semantics::Sqrt
+        | Semantics::SqrtWad
+        | Semantics::Ln
+        | Semantics::LnWad
+        | Semantics::Exp
+        | Semantics::ExpWad
+        | Semantics::Log2 => vec![rounding_direction],
+        Semantics::Abs => vec![handle_abs(&args[0], rounding_direction, context)],
+        Semantics::Min | Semantics::Max => {
+            check_scales(
+                (&args[0], rounding_direction),
+                (&args[1], rounding_direction),
+                context,
+            );
+            vec![rounding_direction, rounding_direction]
+        }
+    };
+    // The remaining arguments, e.g. the scalar of rpow, do not affect the rounding
+    directions.resize(args.len(), Rounding::Init);
+    Ok(directions)
 }
 
 /// This function visits an expression and analyze the rounding direction
@@ -70,8 +434,8 @@ fn handle_pow(
 /// # Arguments
 ///
 /// * `expr` - An expression to be visited.
-/// * `rounding_direction` - A boolean value indicating the rounding direction for arithmetic operations.
-/// * `formula_config` - A reference to a formula_configuration object containing information about rounding.
+/// * `rounding_direction` - The rounding direction expected for the expression.
+/// * `context` - The formula_configuration, the oracle and the findings of the analysis.
 ///
 /// # Returns
 ///
@@ -79,27 +443,87 @@ fn handle_pow(
 ///
 /// # Example
 ///
-/// ```
+/// ```ignore
 /// use analyze_rounding::visit;
 /// use FormulaConfig;
 ///
 /// let expr = Expr::Number(5);
-/// let formula_config = FormulaConfig::new();
-/// visit(&expr, true, &formula_config);
+/// let mut formula_config = FormulaConfig::new();
+/// let mut context = Context { formula_config: &mut formula_config, oracle: &mut ConfigOracle, findings: Findings::default(), location: String::new() };
+/// visit(&expr, Rounding::Up, &mut context);
 /// ```
-fn visit(expr: &Expr, rounding_direction: bool, formula_config: &mut FormulaConfig) -> Result<()> {
+fn visit(expr: &Expr, rounding_direction: Rounding, context: &mut Context) -> Result<()> {
     match expr {
-        Expr::Number(_) | Expr::Id(_) | Expr::Error => (),
+        Expr::Id(name) => context.findings.identifiers.push(Requirement {
+            value: name.clone(),
+            rounding: rounding_direction,
+            location: context.location.clone(),
+        }),
+        Expr::Number(n) => context.findings.literals.push(Requirement {
+            value: n.to_string(),
+            rounding: rounding_direction,
+            location: context.location.clone(),
+        }),
+        Expr::Error => (),
+        // Neg
+        // Up -> -A -> A down
+        // Down -> -A -> A up
+        Expr::Neg(expr) => visit(expr, !rounding_direction, context)?,
         Expr::Op(left, op, right) => {
+            // The operands are not annotated yet, so the operation displays as written
+            let op_location = expr.location();
+            context.location.clone_from(&op_location);
+
             let (left_rounding, right_rounding) = match op {
                 Opcode::Add => (rounding_direction, rounding_direction),
-                Opcode::Sub => (rounding_direction, !rounding_direction),
-                Opcode::Mul(op_rounding) => handle_mul(rounding_direction, op_rounding),
-                Opcode::Div(op_rounding) => handle_div(rounding_direction, op_rounding),
-                Opcode::Pow => handle_pow(left, rounding_direction, formula_config)?,
+                Opcode::Sub => handle_sub(left, right, rounding_direction, context),
+                Opcode::Mul(op_rounding) => handle_mul(rounding_direction, op_rounding, context),
+                Opcode::Div(op_rounding) => handle_div(rounding_direction, op_rounding, context),
+                Opcode::Pow => {
+                    let one = Expr::Number(Literal::from_str("1").unwrap());
+                    handle_pow(left, right, &one, rounding_direction, context)?
+                }
             };
-            visit(left, left_rounding, formula_config)?;
-            visit(right, right_rounding, formula_config)?;
+            if let Opcode::Add | Opcode::Sub = op {
+                check_scales((left, left_rounding), (right, right_rounding), context);
+            }
+            context.location.clone_from(&op_location);
+            visit(left, left_rounding, context)?;
+            context.location = op_location;
+            visit(right, right_rounding, context)?;
+        }
+        // Cond
+        // c ? A : B -> A and B keep the direction, the operands of c are unconstrained
+        Expr::Cond(condition, then, otherwise) => {
+            let cond_location = expr.location();
+            context.location = condition.to_string();
+            check_scales(
+                (&condition.left, Rounding::Init),
+                (&condition.right, Rounding::Init),
+                context,
+            );
+            visit(&condition.left, Rounding::Init, context)?;
+            context.location = condition.to_string();
+            visit(&condition.right, Rounding::Init, context)?;
+            context.location.clone_from(&cond_location);
+            check_scales(
+                (then, rounding_direction),
+                (otherwise, rounding_direction),
+                context,
+            );
+            visit(then, rounding_direction, context)?;
+            context.location = cond_location;
+            visit(otherwise, rounding_direction, context)?;
+        }
+        Expr::Call(name, args, call_rounding) => {
+            let call_location = expr.location();
+            context.location.clone_from(&call_location);
+
+            let directions = handle_call(name, args, rounding_direction, call_rounding, context)?;
+            for (arg, direction) in args.iter().zip(directions) {
+                context.location.clone_from(&call_location);
+                visit(arg, direction, context)?;
+            }
         }
     };
     Ok(())
@@ -112,14 +536,427 @@ fn visit(expr: &Expr, rounding_direction: bool, formula_config: &mut FormulaConf
 /// * `expr` - The expression to analyze.
 /// * `rounding_direction` - The direction of rounding to use.
 /// * `formula_config` - The formula_configuration to use for the analysis.
+/// * `oracle` - The oracle answering the questions the formula_configuration does not cover.
 ///
 /// # Returns
 ///
-/// Returns a `Result` indicating whether the analysis was successful or not.
+/// Returns the findings of the analysis, or an error if the analysis failed.
 pub fn analyze(
     expr: &Expr,
-    rounding_direction: bool,
+    rounding_direction: Rounding,
     formula_config: &mut FormulaConfig,
-) -> Result<()> {
-    visit(expr, rounding_direction, formula_config)
+    oracle: &mut dyn Oracle,
+) -> Result<Findings> {
+    let mut context = Context {
+        formula_config,
+        oracle,
+        findings: Findings::default(),
+        location: expr.location(),
+    };
+    visit(expr, rounding_direction, &mut context)?;
+    Ok(context.findings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::ast::bool_to_rounding;
+    use crate::analyzer::fixtures::{self, config};
+    use crate::analyzer::oracle::{ConfigOracle, NoPromptOracle, ScriptedOracle};
+    use crate::parser::arithmetic;
+
+    fn run(formula_config: &mut FormulaConfig, oracle: &mut dyn Oracle) -> Result<String> {
+        let ast = arithmetic::ExprParser::new()
+            .parse(&formula_config.formula.clone())
+            .unwrap();
+        let round_up = bool_to_rounding(formula_config.round_up);
+        let findings = analyze(&ast, round_up, formula_config, oracle)?;
+        assert!(findings.unresolved.is_empty());
+        Ok(format!("{ast}"))
+    }
+
+    #[test]
+    fn test_analyze_mul_div() {
+        let mut formula_config = config("a * b / c", true);
+        let result = run(&mut formula_config, &mut ConfigOracle).unwrap();
+        assert_eq!(result, "((a *↑ b) /↑ c)");
+    }
+
+    #[test]
+    fn test_analyze_neg() {
+        let mut formula_config = config("-(a * b) / (c * -d)", true);
+        let result = run(&mut formula_config, &mut ConfigOracle).unwrap();
+        assert_eq!(result, "(-(a *↓ b) /↑ (c *↓ -d))");
+    }
+
+    #[test]
+    fn test_analyze_calls() {
+        let mut formula_config = config("mulDivDown(a, b, divWadUp(c, d)) + rpow(e, n, 1)", true);
+        let mut oracle = ScriptedOracle::new([("e", true)]);
+        let ast = arithmetic::ExprParser::new()
+            .parse(&formula_config.formula.clone())
+            .unwrap();
+        let findings = analyze(&ast, Rounding::Up, &mut formula_config, &mut oracle).unwrap();
+        assert_eq!(
+            format!("{ast}"),
+            "(mulDivDown↑(a, b, divWadUp↓(c, d)) + rpow↑(e, n, 1))"
+        );
+        assert_eq!(
+            findings.checks,
+            vec![
+                Check {
+                    operation: "mulDivDown(a, b, divWadUp(c, d))".to_string(),
+                    implemented: Rounding::Down,
+                    required: Rounding::Up,
+                },
+                Check {
+                    operation: "divWadUp(c, d)".to_string(),
+                    implemented: Rounding::Up,
+                    required: Rounding::Down,
+                },
+            ]
+        );
+        let literals: Vec<(&str, Rounding)> = findings
+            .literals
+            .iter()
+            .map(|r| (r.value.as_str(), r.rounding))
+            .collect();
+        assert_eq!(literals, vec![("1", Rounding::Init)]);
+    }
+
+    #[test]
+    fn test_analyze_monotone_functions() {
+        let mut formula_config = config("sqrt(a * b) - ln(c / d) + exp(-e) + abs(f)", true);
+        let result = run(&mut formula_config, &mut ConfigOracle).unwrap();
+        assert_eq!(
+            result,
+            "(((sqrt↑(a *↑ b) - ln↓(c /↓ d)) + exp↑(-e)) + abs↑(f))"
+        );
+
+        let mut formula_config = config("abs(-2) * abs(f)", true);
+        let ast = arithmetic::ExprParser::new()
+            .parse(&formula_config.formula.clone())
+            .unwrap();
+        let findings = analyze(&ast, Rounding::Up, &mut formula_config, &mut ConfigOracle).unwrap();
+        assert_eq!(findings.literals[0].rounding, Rounding::Down);
+        assert_eq!(findings.identifiers[0].rounding, Rounding::Unknown);
+        assert_eq!(
+            findings.undetermined,
+            vec![
+                "The argument f of abs ranges over (-inf, inf), across 0, its rounding is unknown"
+            ]
+        );
+
+        // The ranges give the sign of the argument
+        formula_config.formula = "abs(x) + abs(y * 2)".to_string();
+        formula_config.ranges = Some(fixtures::ranges(&[("x", "[0, 1e18]"), ("y", "[-5, -1]")]));
+        let ast = arithmetic::ExprParser::new()
+            .parse(&formula_config.formula.clone())
+            .unwrap();
+        let findings = analyze(&ast, Rounding::Up, &mut formula_config, &mut ConfigOracle).unwrap();
+        assert!(findings.undetermined.is_empty());
+        assert_eq!(format!("{ast}"), "(abs↑(x) + abs↑(y *↓ 2))");
+    }
+
+    #[test]
+    fn test_analyze_conditional() {
+        let mut formula_config = config("a / b > c ** d ? min(e * f, g) - h : 0", true);
+        let ast = arithmetic::ExprParser::new()
+            .parse(&formula_config.formula.clone())
+            .unwrap();
+        let findings = analyze(&ast, Rounding::Up, &mut formula_config, &mut ConfigOracle).unwrap();
+        assert_eq!(
+            format!("{ast}"),
+            "((a / b) > (c ** d) ? (min↑(e *↑ f, g) - h) : 0)"
+        );
+        let identifiers: Vec<(&str, Rounding)> = findings
+            .identifiers
+            .iter()
+            .map(|r| (r.value.as_str(), r.rounding))
+            .collect();
+        assert_eq!(
+            identifiers,
+            vec![
+                ("a", Rounding::Init),
+                ("b", Rounding::Init),
+                ("c", Rounding::Init),
+                ("d", Rounding::Init),
+                ("e", Rounding::Up),
+                ("f", Rounding::Up),
+                ("g", Rounding::Up),
+                ("h", Rounding::Down),
+            ]
+        );
+        assert_eq!(findings.literals[0].rounding, Rounding::Up);
+        assert_eq!(
+            findings.literals[0].location,
+            "(a / b) > (c ** d) ? (min(e * f, g) - h) : 0"
+        );
+    }
+
+    #[test]
+    fn test_analyze_pow_scripted() {
+        let mut formula_config = config("(a / b) ** (c * d)", true);
+        let mut oracle = ScriptedOracle::new([("(a / b)", false)]);
+        let result = run(&mut formula_config, &mut oracle).unwrap();
+        assert_eq!(result, "((a /↑ b) ** (c *↓ d))");
+        assert_eq!(
+            formula_config.less_than_one,
+            Some(vec!["(a / b)".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_analyze_pow_ranges() {
+        let mut formula_config = config("(bi / (bi + ai)) ** (w * x) + (y + 1) ** (w * x)", true);
+        let ranges = [("ai", "[0, inf)"), ("bi", "[0, inf)"), ("y", "[0, 1]")];
+        formula_config.ranges = Some(fixtures::ranges(&ranges));
+        let result = run(&mut formula_config, &mut ConfigOracle).unwrap();
+        assert_eq!(
+            result,
+            "(((bi /↑ (bi + ai)) ** (w *↓ x)) + ((y + 1) ** (w *↑ x)))"
+        );
+        assert_eq!(formula_config.less_than_one, None);
+
+        // The range of y does not place y * 2 relative to 1, only the lists can
+        formula_config.formula = "(y * 2) ** (w * x)".to_string();
+        let result = run(&mut formula_config, &mut ConfigOracle).unwrap();
+        assert_eq!(result, "((y *↑ 2) ** (w *↕ x))");
+        formula_config.add_less_than_one("(y * 2)".to_string());
+        let result = run(&mut formula_config, &mut ConfigOracle).unwrap();
+        assert_eq!(result, "((y *↑ 2) ** (w *↓ x))");
+    }
+
+    #[test]
+    fn test_analyze_pow_negated_exponent() {
+        // Without a range, the sign of a negated exponent is unknown
+        let mut formula_config = config("(x * y) ** -b", true);
+        formula_config.greater_than_one = Some(vec!["(x * y)".to_string()]);
+        let ast = arithmetic::ExprParser::new()
+            .parse(&formula_config.formula.clone())
+            .unwrap();
+        let findings =
+            analyze(&ast, Rounding::Up, &mut formula_config, &mut NoPromptOracle).unwrap();
+        assert_eq!(format!("{ast}"), "((x *↕ y) ** -b)");
+        assert_eq!(
+            findings.undetermined,
+            vec!["The exponent -b can be negative without declared ranges, the rounding of the base (x * y) is unknown"]
+        );
+
+        // A range places it
+        formula_config.ranges = Some(fixtures::ranges(&[("b", "[0, 10]")]));
+        let result = run(&mut formula_config, &mut NoPromptOracle).unwrap();
+        assert_eq!(result, "((x *↓ y) ** -b)");
+    }
+
+    #[test]
+    fn test_analyze_pow_wad() {
+        // The fixed-point bases are compared with their scalar, not with 1
+        let mut formula_config = config(
+            "powDown(x * a, w * b) + rpow(x * a, n, 1e27) + (x * a) ** (w * b)",
+            false,
+        );
+        let ranges = [
+            ("x", "[0, 1e18]"),
+            ("a", "[0, 1]"),
+            ("w", "[0, inf)"),
+            ("b", "[0, inf)"),
+        ];
+        formula_config.ranges = Some(fixtures::ranges(&ranges));
+        let ast = arithmetic::ExprParser::new()
+            .parse(&formula_config.formula.clone())
+            .unwrap();
+        let findings = analyze(
+            &ast,
+            Rounding::Down,
+            &mut formula_config,
+            &mut NoPromptOracle,
+        )
+        .unwrap();
+        assert_eq!(
+            format!("{ast}"),
+            "((powDown↓(x *↓ a, w *↑ b) + rpow↓(x *↓ a, n, 1e27)) + ((x *↓ a) ** (w *↕ b)))"
+        );
+        assert!(findings.unresolved.is_empty());
+        assert_eq!(
+            findings.undetermined,
+            vec!["The base (x * a) ranges over [0, 1000000000000000000], across 1, the rounding of its exponent is unknown"]
+        );
+    }
+
+    #[test]
+    fn test_analyze_pow_signs() {
+        // A negative exponent reverses the rounding of the base
+        let mut formula_config = config("a ** -1 + (b / c) ** (0 - d)", true);
+        formula_config.ranges = Some(fixtures::ranges(&[("d", "[0, 1]")]));
+        let mut oracle = ScriptedOracle::new([("a", true), ("(b / c)", false)]);
+        let result = run(&mut formula_config, &mut oracle).unwrap();
+        assert_eq!(result, "((a ** -1) + ((b /↓ c) ** (0 - d)))");
+
+        // Exponents and bases straddling their threshold make the rounding unknown
+        let mut formula_config = config("(a * b) ** (c * d)", false);
+        formula_config.ranges = Some(fixtures::ranges(&[
+            ("a", "[0, 1]"),
+            ("b", "[1, 3]"),
+            ("c", "[-1, 1]"),
+        ]));
+        let ast = arithmetic::ExprParser::new()
+            .parse(&formula_config.formula.clone())
+            .unwrap();
+        let findings = analyze(
+            &ast,
+            Rounding::Down,
+            &mut formula_config,
+            &mut NoPromptOracle,
+        )
+        .unwrap();
+        assert_eq!(format!("{ast}"), "((a *↕ b) ** (c *↕ d))");
+        assert!(findings.unresolved.is_empty());
+        assert_eq!(
+            findings.undetermined,
+            vec![
+                "The exponent (c * d) ranges over (-inf, inf), the rounding of the base (a * b) is unknown",
+                "The base (a * b) ranges over [0, 3], across 1, the rounding of its exponent is unknown",
+            ]
+        );
+
+        // A base unbounded by the identifiers without a range is placed by the oracle
+        formula_config.ranges = Some(fixtures::ranges(&[("a", "[0, 1]")]));
+        let mut oracle = ScriptedOracle::new([("(a * b)", true)]);
+        let result = run(&mut formula_config, &mut oracle).unwrap();
+        assert_eq!(result, "((a *↓ b) ** (c *↓ d))");
+        assert_eq!(
+            formula_config.greater_than_one,
+            Some(vec!["(a * b)".to_string()])
+        );
+
+        // Nothing is asked when the direction required is already unknown
+        let mut formula_config = config("(a * b) ** c", false);
+        let ast = arithmetic::ExprParser::new()
+            .parse(&formula_config.formula.clone())
+            .unwrap();
+        let mut oracle = ScriptedOracle::new::<_, &str>([]);
+        let findings = analyze(&ast, Rounding::Unknown, &mut formula_config, &mut oracle).unwrap();
+        assert_eq!(format!("{ast}"), "((a *↕ b) ** c)");
+        assert!(findings.unresolved.is_empty());
+        assert!(formula_config.greater_than_one.is_none());
+    }
+
+    #[test]
+    fn test_analyze_underflows() {
+        let mut formula_config = config(
+            "b * (1 - x ** y) + (c - b) + (1 - x) * (2 - x) + (d - 1)",
+            false,
+        );
+        formula_config.ranges = Some(fixtures::ranges(&[
+            ("b", "[0, 10]"),
+            ("c", "[5, 20]"),
+            ("x", "[0, 1]"),
+            ("y", "[1, 2]"),
+        ]));
+        let ast = arithmetic::ExprParser::new()
+            .parse(&formula_config.formula.clone())
+            .unwrap();
+        let findings =
+            analyze(&ast, Rounding::Down, &mut formula_config, &mut ConfigOracle).unwrap();
+        assert_eq!(
+            findings.underflows,
+            vec![
+                "1 - (x ** y) can underflow, it can be 0 and 1 rounds down while (x ** y) rounds up",
+                "c - b can underflow, it ranges over [-5, 20]",
+            ]
+        );
+
+        // 1 - x can be 0, but neither 1 nor x rounds
+        formula_config.formula = "1 - x".to_string();
+        let ast = arithmetic::ExprParser::new()
+            .parse(&formula_config.formula.clone())
+            .unwrap();
+        let findings =
+            analyze(&ast, Rounding::Down, &mut formula_config, &mut ConfigOracle).unwrap();
+        assert!(findings.underflows.is_empty());
+
+        // Rounding 1 - x up cannot make it negative
+        formula_config.formula = "1 - x".to_string();
+        let ast = arithmetic::ExprParser::new()
+            .parse(&formula_config.formula.clone())
+            .unwrap();
+        let findings = analyze(&ast, Rounding::Up, &mut formula_config, &mut ConfigOracle).unwrap();
+        assert!(findings.underflows.is_empty());
+    }
+
+    #[test]
+    fn test_analyze_scales() {
+        let mut formula_config = config("a - r + (a > amount ? min(a, amount) : r)", false);
+        formula_config.decimals = Some(
+            [("a", 18), ("r", 27), ("amount", 6)]
+                .iter()
+                .map(|(name, decimals)| (name.to_string(), *decimals))
+                .collect(),
+        );
+        let ast = arithmetic::ExprParser::new()
+            .parse(&formula_config.formula.clone())
+            .unwrap();
+        let findings =
+            analyze(&ast, Rounding::Down, &mut formula_config, &mut ConfigOracle).unwrap();
+        let mismatches: Vec<String> = findings
+            .scale_mismatches
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            mismatches,
+            vec![
+                "a - r: r has 27 decimals instead of 18, rescaling it divides by 1e9 rounding up",
+                "a > amount: a has 18 decimals instead of 6, rescaling it divides by 1e12 rounding either",
+                "a > amount ? min(a, amount) : r: r has 27 decimals instead of 18, rescaling it divides by 1e9 rounding down",
+                "min(a, amount): a has 18 decimals instead of 6, rescaling it divides by 1e12 rounding down",
+            ]
+        );
+
+        // A fee in basis points is not a rescaling
+        formula_config.formula = "a * 30 / 10000 + a".to_string();
+        let ast = arithmetic::ExprParser::new()
+            .parse(&formula_config.formula.clone())
+            .unwrap();
+        let findings =
+            analyze(&ast, Rounding::Down, &mut formula_config, &mut ConfigOracle).unwrap();
+        assert!(findings.scale_mismatches.is_empty());
+    }
+
+    #[test]
+    fn test_analyze_pow_config_only() {
+        let mut formula_config = config("a ** (c * d)", false);
+        assert!(run(&mut formula_config, &mut ConfigOracle).is_err());
+
+        formula_config.add_greater_than_one("a".to_string());
+        let result = run(&mut formula_config, &mut ConfigOracle).unwrap();
+        assert_eq!(result, "(a ** (c *↓ d))");
+    }
+
+    #[test]
+    fn test_analyze_pow_no_prompt() {
+        let mut formula_config = config("(a * b) ** (c / d)", true);
+        let ast = arithmetic::ExprParser::new()
+            .parse(&formula_config.formula.clone())
+            .unwrap();
+        let findings =
+            analyze(&ast, Rounding::Up, &mut formula_config, &mut NoPromptOracle).unwrap();
+        assert_eq!(format!("{ast}"), "((a *↑ b) ** (c /↕ d))");
+        assert_eq!(findings.unresolved, vec!["(a * b)"]);
+        let identifiers: Vec<(&str, Rounding, &str)> = findings
+            .identifiers
+            .iter()
+            .map(|r| (r.value.as_str(), r.rounding, r.location.as_str()))
+            .collect();
+        assert_eq!(
+            identifiers,
+            vec![
+                ("a", Rounding::Up, "a * b"),
+                ("b", Rounding::Up, "a * b"),
+                ("c", Rounding::Unknown, "c / d"),
+                ("d", Rounding::Unknown, "c / d"),
+            ]
+        );
+    }
 }
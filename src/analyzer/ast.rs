@@ -1,23 +1,137 @@
 use std::cell::RefCell;
 use std::fmt::{Debug, Display, Error, Formatter};
+use std::ops::{Neg, Not};
+use std::str::FromStr;
 
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{Num, Signed};
 use serde::{Deserialize, Serialize};
 
 /// Represents an expression in the AST.
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum Expr {
     /// A numeric literal.
-    Number(i32),
+    Number(Literal),
     /// An identifier.
     Id(String),
+    /// The negation of an expression.
+    Neg(Box<Expr>),
     /// An operation with two operands.
     Op(Box<Expr>, Opcode, Box<Expr>),
+    /// A call to a function of the `builtins` catalogue, with the rounding direction
+    /// determined for its result.
+    Call(String, Vec<Expr>, RefCell<Rounding>),
+    /// A conditional expression, `condition ? then : otherwise`.
+    Cond(Box<Condition>, Box<Expr>, Box<Expr>),
     /// An error expression.
     Error,
 }
 
+/// A numeric literal, with its exact value.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Literal {
+    /// The exact value of the literal.
+    pub value: BigRational,
+    /// The literal as written in the formula.
+    pub text: String,
+}
+
+/// The largest exponent of the powers computed exactly: the scientific notation of the
+/// literals, and `**` when evaluating a formula or bounding its magnitudes and errors.
+pub const MAX_EXPONENT: u32 = 1024;
+
+impl FromStr for Literal {
+    type Err = &'static str;
+
+    /// Parses an integer (`1000`), a decimal (`0.997`), a number in scientific notation
+    /// (`1e18`, `2.5e-3`) or a hexadecimal integer (`0xff`). Digits can be separated
+    /// with underscores (`1_000_000`).
+    fn from_str(text: &str) -> Result<Literal, &'static str> {
+        let digits = text.replace('_', "");
+
+        let value = if let Some(hex) = digits
+            .strip_prefix("0x")
+            .or_else(|| digits.strip_prefix("0X"))
+        {
+            BigInt::from_str_radix(hex, 16)
+                .map(BigRational::from_integer)
+                .map_err(|_| "invalid hexadecimal number")?
+        } else {
+            let (mantissa, exponent) = match digits.split_once(['e', 'E']) {
+                Some((mantissa, exponent)) => (
+                    mantissa,
+                    exponent.parse::<i64>().map_err(|_| "invalid exponent")?,
+                ),
+                None => (digits.as_str(), 0),
+            };
+            let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+
+            let mantissa =
+                BigInt::from_str(&format!("{integer}{fraction}")).map_err(|_| "invalid number")?;
+            let exponent = i64::try_from(fraction.len())
+                .ok()
+                .and_then(|digits| exponent.checked_sub(digits))
+                .ok_or("number is too big")?;
+            let scale = u32::try_from(exponent.unsigned_abs())
+                .ok()
+                .filter(|scale| *scale <= MAX_EXPONENT)
+                .ok_or("number is too big")?;
+            let scale = BigInt::from(10).pow(scale);
+
+            if exponent >= 0 {
+                BigRational::from_integer(mantissa * scale)
+            } else {
+                BigRational::new(mantissa, scale)
+            }
+        };
+
+        Ok(Literal {
+            value,
+            text: text.to_string(),
+        })
+    }
+}
+
+impl From<i32> for Literal {
+    fn from(n: i32) -> Literal {
+        Literal {
+            value: BigRational::from_integer(BigInt::from(n)),
+            text: n.to_string(),
+        }
+    }
+}
+
+impl Neg for Literal {
+    type Output = Literal;
+
+    fn neg(self) -> Literal {
+        let text = match self.text.strip_prefix('-') {
+            Some(text) => text.to_string(),
+            None => format!("-{}", self.text),
+        };
+        Literal {
+            value: -self.value,
+            text,
+        }
+    }
+}
+
+impl Literal {
+    /// Returns whether the literal is negative.
+    pub fn is_negative(&self) -> bool {
+        self.value.is_negative()
+    }
+}
+
+impl Display for Literal {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        write!(fmt, "{}", self.text)
+    }
+}
+
 /// Represents the possible rounding modes.
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 pub enum Rounding {
     /// The initial rounding mode.
     Init,
@@ -29,6 +143,31 @@ pub enum Rounding {
     Unknown,
 }
 
+impl Display for Rounding {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        match &self {
+            Rounding::Init => write!(fmt, "either"),
+            Rounding::Up => write!(fmt, "up"),
+            Rounding::Down => write!(fmt, "down"),
+            Rounding::Unknown => write!(fmt, "unknown"),
+        }
+    }
+}
+
+/// Inverts the rounding direction, `Init` and `Unknown` are left unchanged.
+impl Not for Rounding {
+    type Output = Rounding;
+
+    fn not(self) -> Rounding {
+        match self {
+            Rounding::Up => Rounding::Down,
+            Rounding::Down => Rounding::Up,
+            Rounding::Init => Rounding::Init,
+            Rounding::Unknown => Rounding::Unknown,
+        }
+    }
+}
+
 /// Converts a boolean flag to a rounding mode.
 ///
 /// # Arguments
@@ -46,7 +185,7 @@ pub fn bool_to_rounding(flag: bool) -> Rounding {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum Opcode {
     // The rounding direction of the mul/div operation will be determined on the fly
     // Might not be the most rust thing to do
@@ -58,17 +197,90 @@ pub enum Opcode {
     Pow,
 }
 
+/// The condition of a conditional expression.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Condition {
+    pub left: Box<Expr>,
+    pub comparison: Comparison,
+    pub right: Box<Expr>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Comparison {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+impl Expr {
+    /// Displays the operation without the parentheses surrounding it, e.g. `a * b` for
+    /// `(a * b)`, to locate it in the reports.
+    pub fn location(&self) -> String {
+        let expr_str = self.to_string();
+        match self {
+            Expr::Op(..) | Expr::Cond(..) => expr_str[1..expr_str.len() - 1].to_string(),
+            _ => expr_str,
+        }
+    }
+}
+
 impl Display for Expr {
     fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
         match &self {
             Expr::Number(n) => write!(fmt, "{n}"),
             Expr::Id(ref n) => write!(fmt, "{n}"),
+            Expr::Neg(ref e) => write!(fmt, "-{e}"),
             Expr::Op(ref l, op, ref r) => write!(fmt, "({l} {op} {r})"),
+            Expr::Call(name, args, r) => {
+                let direction = match *r.borrow() {
+                    Rounding::Init => "",
+                    Rounding::Up => "↑",
+                    Rounding::Down => "↓",
+                    Rounding::Unknown => "↕",
+                };
+                // The parentheses of the call already delimit the operations
+                let args: Vec<String> = args
+                    .iter()
+                    .map(|arg| {
+                        let arg_str = arg.to_string();
+                        match arg {
+                            Expr::Op(..) => arg_str[1..arg_str.len() - 1].to_string(),
+                            _ => arg_str,
+                        }
+                    })
+                    .collect();
+                write!(fmt, "{name}{direction}({})", args.join(", "))
+            }
+            Expr::Cond(condition, then, otherwise) => {
+                write!(fmt, "({condition} ? {then} : {otherwise})")
+            }
             Expr::Error => write!(fmt, "error"),
         }
     }
 }
 
+impl Display for Condition {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        write!(fmt, "{} {} {}", self.left, self.comparison, self.right)
+    }
+}
+
+impl Display for Comparison {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        match &self {
+            Comparison::Lt => write!(fmt, "<"),
+            Comparison::Le => write!(fmt, "<="),
+            Comparison::Gt => write!(fmt, ">"),
+            Comparison::Ge => write!(fmt, ">="),
+            Comparison::Eq => write!(fmt, "=="),
+            Comparison::Ne => write!(fmt, "!="),
+        }
+    }
+}
+
 impl Display for Opcode {
     fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
         match &self {
@@ -98,7 +310,7 @@ mod tests {
 
     #[test]
     fn test_display_number() {
-        let expr = Expr::Number(42);
+        let expr = Expr::Number(42.into());
         assert_eq!(format!("{expr}"), "42");
     }
 
@@ -110,11 +322,66 @@ mod tests {
 
     #[test]
     fn test_display_op() {
-        let left = Expr::Number(1);
-        let right = Expr::Number(2);
+        let left = Expr::Number(1.into());
+        let right = Expr::Number(2.into());
         let op = Opcode::Add;
         let expr = Expr::Op(Box::new(left), op, Box::new(right));
         assert_eq!(format!("{expr}"), "(1 + 2)");
+        assert_eq!(expr.location(), "1 + 2");
+    }
+
+    fn literal(text: &str) -> BigRational {
+        Literal::from_str(text).unwrap().value
+    }
+
+    fn ratio(numer: i64, denom: i64) -> BigRational {
+        BigRational::new(BigInt::from(numer), BigInt::from(denom))
+    }
+
+    #[test]
+    fn test_literal_from_str() {
+        assert_eq!(literal("42"), ratio(42, 1));
+        assert_eq!(literal("0.997"), ratio(997, 1000));
+        assert_eq!(literal("1_000_000"), ratio(1_000_000, 1));
+        assert_eq!(literal("2.5e-3"), ratio(1, 400));
+        assert_eq!(literal("1E6"), ratio(1_000_000, 1));
+        assert_eq!(literal("0xff"), ratio(255, 1));
+        assert_eq!(
+            literal("1e27"),
+            BigRational::from_integer(BigInt::from(10).pow(27))
+        );
+        assert_eq!(
+            literal("0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff"),
+            BigRational::from_integer((BigInt::from(1) << 256) - 1)
+        );
+        assert!(Literal::from_str("1e100000").is_err());
+        assert!(Literal::from_str("1e-9223372036854775808").is_err());
+        assert!(Literal::from_str("0.25e-9223372036854775807").is_err());
+    }
+
+    #[test]
+    fn test_literal_neg() {
+        let n = -Literal::from_str("1e18").unwrap();
+        assert_eq!(format!("{n}"), "-1e18");
+        assert!(n.is_negative());
+        assert_eq!(-n, Literal::from_str("1e18").unwrap());
+    }
+
+    #[test]
+    fn test_display_neg() {
+        let expr = Expr::Neg(Box::new(Expr::Id("x".to_string())));
+        assert_eq!(format!("{expr}"), "-x");
+    }
+
+    #[test]
+    fn test_display_call() {
+        let args = vec![Expr::Id("a".to_string()), Expr::Number(2.into())];
+        let expr = Expr::Call("mulWadUp".to_string(), args, RefCell::new(Rounding::Init));
+        assert_eq!(format!("{expr}"), "mulWadUp(a, 2)");
+        if let Expr::Call(_, _, r) = &expr {
+            *r.borrow_mut() = Rounding::Down;
+        }
+        assert_eq!(format!("{expr}"), "mulWadUp↓(a, 2)");
     }
 
     #[test]
@@ -177,6 +444,22 @@ mod tests {
         assert_eq!(bool_to_rounding(false), Rounding::Down);
     }
 
+    #[test]
+    fn test_display_rounding() {
+        assert_eq!(format!("{}", Rounding::Init), "either");
+        assert_eq!(format!("{}", Rounding::Up), "up");
+        assert_eq!(format!("{}", Rounding::Down), "down");
+        assert_eq!(format!("{}", Rounding::Unknown), "unknown");
+    }
+
+    #[test]
+    fn test_not_rounding() {
+        assert_eq!(!Rounding::Up, Rounding::Down);
+        assert_eq!(!Rounding::Down, Rounding::Up);
+        assert_eq!(!Rounding::Init, Rounding::Init);
+        assert_eq!(!Rounding::Unknown, Rounding::Unknown);
+    }
+
     #[test]
     fn test_display_opcode() {
         let rounding = RefCell::new(Rounding::Up);
@@ -0,0 +1,139 @@
+use num_bigint::BigInt;
+use num_rational::BigRational;
+
+use super::ast::Rounding;
+
+/// The scalar of the WAD numbers, `1e18` standing for 1.
+pub fn wad() -> BigRational {
+    BigRational::from_integer(BigInt::from(10).pow(18))
+}
+
+/// What a built-in function computes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Semantics {
+    /// `a * b / c`
+    MulDiv,
+    /// `a * b / 1e18`
+    MulWad,
+    /// `a * 1e18 / b`
+    DivWad,
+    /// `a / b`
+    Div,
+    /// `x ** y`
+    Pow,
+    /// `x ** y` on WADs, `(x / 1e18) ** (y / 1e18) * 1e18`
+    PowWad,
+    /// `x ** n` on numbers scaled by the third argument, `(x / scalar) ** n * scalar`
+    RPow,
+    /// `√x`
+    Sqrt,
+    /// `√x` on WADs, `√(x * 1e18)`
+    SqrtWad,
+    /// `ln(x)`
+    Ln,
+    /// `ln(x)` on WADs, `ln(x / 1e18) * 1e18`
+    LnWad,
+    /// `e ** x`
+    Exp,
+    /// `e ** x` on WADs, `e ** (x / 1e18) * 1e18`
+    ExpWad,
+    /// `log2(x)`
+    Log2,
+    /// `|x|`
+    Abs,
+    /// The smallest of the arguments
+    Min,
+    /// The largest of the arguments
+    Max,
+}
+
+/// A function of the fixed-point libraries used by the contracts.
+#[derive(Debug)]
+pub struct Builtin {
+    /// The name of the function, without the library prefix.
+    pub name: &'static str,
+    /// What the function computes.
+    pub semantics: Semantics,
+    /// The number of arguments.
+    pub arity: usize,
+    /// The rounding direction implemented by the function, `None` if it does not round
+    /// in a fixed direction.
+    pub rounding: Option<Rounding>,
+}
+
+const fn builtin(
+    name: &'static str,
+    semantics: Semantics,
+    arity: usize,
+    rounding: Option<Rounding>,
+) -> Builtin {
+    Builtin {
+        name,
+        semantics,
+        arity,
+        rounding,
+    }
+}
+
+/// The catalogue of the known functions.
+pub const BUILTINS: &[Builtin] = &[
+    // FullMath (Uniswap), Math (OpenZeppelin), FixedPointMathLib (solmate, solady)
+    builtin("mulDiv", Semantics::MulDiv, 3, Some(Rounding::Down)),
+    builtin("mulDivDown", Semantics::MulDiv, 3, Some(Rounding::Down)),
+    builtin("mulDivUp", Semantics::MulDiv, 3, Some(Rounding::Up)),
+    builtin("mulDivRoundingUp", Semantics::MulDiv, 3, Some(Rounding::Up)),
+    builtin("fullMulDiv", Semantics::MulDiv, 3, Some(Rounding::Down)),
+    builtin("fullMulDivUp", Semantics::MulDiv, 3, Some(Rounding::Up)),
+    builtin("mulWad", Semantics::MulWad, 2, Some(Rounding::Down)),
+    builtin("mulWadDown", Semantics::MulWad, 2, Some(Rounding::Down)),
+    builtin("mulWadUp", Semantics::MulWad, 2, Some(Rounding::Up)),
+    builtin("divWad", Semantics::DivWad, 2, Some(Rounding::Down)),
+    builtin("divWadDown", Semantics::DivWad, 2, Some(Rounding::Down)),
+    builtin("divWadUp", Semantics::DivWad, 2, Some(Rounding::Up)),
+    builtin("ceilDiv", Semantics::Div, 2, Some(Rounding::Up)),
+    // rpow(x, n, scalar) rounds to the nearest value
+    builtin("rpow", Semantics::RPow, 3, None),
+    // FixedPoint (Balancer)
+    builtin("mulDown", Semantics::MulWad, 2, Some(Rounding::Down)),
+    builtin("mulUp", Semantics::MulWad, 2, Some(Rounding::Up)),
+    builtin("divDown", Semantics::DivWad, 2, Some(Rounding::Down)),
+    builtin("divUp", Semantics::DivWad, 2, Some(Rounding::Up)),
+    builtin("powDown", Semantics::PowWad, 2, Some(Rounding::Down)),
+    builtin("powUp", Semantics::PowWad, 2, Some(Rounding::Up)),
+    // LogExpMath (Balancer), FixedPointMathLib (solmate, solady), Math (OpenZeppelin)
+    builtin("pow", Semantics::Pow, 2, None),
+    builtin("powWad", Semantics::PowWad, 2, None),
+    builtin("sqrt", Semantics::Sqrt, 1, None),
+    builtin("sqrtWad", Semantics::SqrtWad, 1, None),
+    builtin("ln", Semantics::Ln, 1, None),
+    builtin("lnWad", Semantics::LnWad, 1, None),
+    builtin("exp", Semantics::Exp, 1, None),
+    builtin("expWad", Semantics::ExpWad, 1, None),
+    builtin("log2", Semantics::Log2, 1, None),
+    builtin("abs", Semantics::Abs, 1, None),
+    builtin("min", Semantics::Min, 2, None),
+    builtin("max", Semantics::Max, 2, None),
+];
+
+/// Finds a function by name. The library prefix is ignored, so `FullMath.mulDiv` is
+/// `mulDiv`.
+pub fn lookup(name: &str) -> Option<&'static Builtin> {
+    let name = name.rsplit('.').next().unwrap_or(name);
+    BUILTINS.iter().find(|builtin| builtin.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        let builtin = lookup("FixedPointMathLib.mulWadUp").unwrap();
+        assert_eq!(builtin.semantics, Semantics::MulWad);
+        assert_eq!(builtin.rounding, Some(Rounding::Up));
+        assert_eq!(lookup("mulDiv").unwrap().arity, 3);
+        assert_eq!(lookup("powDown").unwrap().semantics, Semantics::PowWad);
+        assert_eq!(lookup("sqrtWad").unwrap().semantics, Semantics::SqrtWad);
+        assert!(lookup("unknown").is_none());
+    }
+}
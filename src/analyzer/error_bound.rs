@@ -0,0 +1,409 @@
+use std::fmt::{Display, Error, Formatter};
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+use super::ast::{Expr, Opcode, MAX_EXPONENT};
+use super::builtins::{self, Semantics};
+use super::interval::{self, Interval, Ranges};
+
+/// The number of significant digits of the displayed bounds.
+const DIGITS: u32 = 4;
+
+/// How far an intermediate value of the formula can be from its exact value, because of
+/// the roundings of the operations computing it.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ErrorBound {
+    /// The operation computing the value, as written in the formula.
+    pub expression: String,
+    /// The largest absolute error, `None` if it is unbounded.
+    pub absolute: Option<BigRational>,
+    /// The largest error relative to the exact value, `None` if it is unbounded or if
+    /// the value can be 0.
+    pub relative: Option<BigRational>,
+}
+
+/// Displays a positive bound rounded up to `DIGITS` significant digits, e.g. `1.334` for
+/// 4/3 and `2.5e-16` for 1/4e15. The integers below 1e6 are displayed as they are.
+pub fn approximate(value: &BigRational) -> String {
+    if value.is_zero() {
+        return "0".to_string();
+    }
+    if value.is_integer() && value.to_integer() < BigInt::from(1_000_000) {
+        return value.to_string();
+    }
+
+    let ten = BigRational::from_integer(10.into());
+    let power = |exponent: i64| num_traits::pow::Pow::pow(&ten, exponent);
+    // 10^exponent <= value < 10^(exponent + 1)
+    let mut exponent = i64::try_from(value.numer().to_string().len()).unwrap_or(0)
+        - i64::try_from(value.denom().to_string().len()).unwrap_or(0);
+    while power(exponent) > *value {
+        exponent -= 1;
+    }
+    while power(exponent + 1) <= *value {
+        exponent += 1;
+    }
+
+    let mut mantissa = (value / power(exponent - i64::from(DIGITS) + 1))
+        .ceil()
+        .to_integer();
+    if mantissa == BigInt::from(10).pow(DIGITS) {
+        mantissa /= 10;
+        exponent += 1;
+    }
+    let digits = mantissa.to_string();
+    let fraction = digits[1..].trim_end_matches('0');
+    let mut text = digits[..1].to_string();
+    if !fraction.is_empty() {
+        text = format!("{text}.{fraction}");
+    }
+    if exponent != 0 {
+        text = format!("{text}e{exponent}");
+    }
+    text
+}
+
+impl Display for ErrorBound {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        match &self.absolute {
+            Some(absolute) => write!(fmt, "{}: ±{}", self.expression, approximate(absolute))?,
+            None => write!(fmt, "{}: unbounded", self.expression)?,
+        }
+        if let Some(relative) = &self.relative {
+            write!(fmt, " (relative ±{})", approximate(relative))?;
+        }
+        Ok(())
+    }
+}
+
+/// A value: the range of its exact values, and the largest error of its computed value.
+struct Value {
+    range: Interval,
+    error: Option<BigRational>,
+}
+
+impl Value {
+    fn exact(range: Interval) -> Value {
+        Value {
+            range,
+            error: Some(BigRational::zero()),
+        }
+    }
+
+    /// `|x|` times the error of the other operand, 0 if that error is 0 even if `x` is
+    /// unbounded.
+    fn scale(&self, error: &Option<BigRational>) -> Option<BigRational> {
+        match error {
+            Some(error) if error.is_zero() => Some(BigRational::zero()),
+            Some(error) => Some(self.range.max_abs()? * error),
+            None => None,
+        }
+    }
+}
+
+/// The rounding error of an operation rounding its result to an integer.
+fn ulp() -> Option<BigRational> {
+    Some(BigRational::one())
+}
+
+fn add(a: &Option<BigRational>, b: &Option<BigRational>) -> Option<BigRational> {
+    Some(a.as_ref()? + b.as_ref()?)
+}
+
+fn max(a: &Option<BigRational>, b: &Option<BigRational>) -> Option<BigRational> {
+    let (a, b) = (a.as_ref()?, b.as_ref()?);
+    Some(if a > b { a } else { b }.clone())
+}
+
+/// `|ab - a'b'| <= |a| Eb + |b| Ea + Ea Eb`, the product itself being exact.
+fn mul(a: &Value, b: &Value) -> Value {
+    let cross = match (&a.error, &b.error) {
+        (Some(ea), Some(eb)) => Some(ea * eb),
+        _ => None,
+    };
+    Value {
+        range: a.range.mul(&b.range),
+        error: add(&add(&a.scale(&b.error), &b.scale(&a.error)), &cross),
+    }
+}
+
+/// `|a/b - a'/b'| <= Ea / |b'| + |a| Eb / (|b| |b'|)`, plus the rounding of the
+/// division. The divisors are integers, at least 1 in absolute value as dividing by 0
+/// reverts.
+fn div(a: &Value, b: &Value) -> Value {
+    let one = BigRational::one();
+    let divisor = b.range.min_abs().max(one.clone());
+    let error = b.error.as_ref().and_then(|eb| {
+        let computed = (&divisor - eb).max(one);
+        let numerator = a.error.as_ref()? / &computed;
+        let denominator = a.scale(&b.error)? / (&divisor * &computed);
+        Some(numerator + denominator)
+    });
+    Value {
+        range: a.range.div(&b.range),
+        error: add(&error, &ulp()),
+    }
+}
+
+/// `a ** b`: exact for exact operands and a non-negative exponent, the integer
+/// exponentiation not rounding, and `(|a| + Ea)^n - |a|^n` with an error on the base and
+/// a literal exponent `n`. A power with an exponent that can be negative is rounded once,
+/// and the other powers of operands with errors are unbounded.
+fn pow(a: &Value, exponent: &Expr, b: &Value) -> Value {
+    let range = a.range.pow(&b.range);
+    let exponent = match exponent {
+        Expr::Number(n) if n.value.is_integer() && !n.value.is_negative() => {
+            n.value.to_integer().to_u32()
+        }
+        _ => None,
+    };
+    let error = match (exponent, &a.error) {
+        (Some(_), Some(ea)) if ea.is_zero() => Some(BigRational::zero()),
+        (Some(n), Some(ea)) if n <= MAX_EXPONENT => a
+            .range
+            .max_abs()
+            .map(|max| num_traits::pow(&max + ea, n as usize) - num_traits::pow(max, n as usize)),
+        (None, Some(ea)) if ea.is_zero() && b.error.as_ref().is_some_and(Zero::is_zero) => {
+            if b.range.is_non_negative() {
+                Some(BigRational::zero())
+            } else {
+                ulp()
+            }
+        }
+        _ => None,
+    };
+    Value { range, error }
+}
+
+/// A function whose slope is at most `slope` over the values: the error is scaled by the
+/// slope, plus the rounding of the result.
+fn lipschitz(x: &Value, range: Interval, slope: Option<BigRational>) -> Value {
+    let error = match &x.error {
+        Some(error) if error.is_zero() => Some(BigRational::zero()),
+        Some(error) => slope.map(|slope| slope * error),
+        None => None,
+    };
+    Value {
+        range,
+        error: add(&error, &ulp()),
+    }
+}
+
+struct Context<'a> {
+    ranges: &'a Ranges,
+    bounds: Vec<ErrorBound>,
+}
+
+/// The relative error of a value, `None` if it can be 0.
+fn relative(value: &Value) -> Option<BigRational> {
+    let min = value.range.min_abs();
+    if min.is_zero() {
+        return None;
+    }
+    Some(value.error.as_ref()? / min)
+}
+
+fn visit(expr: &Expr, context: &mut Context) -> Value {
+    let value = match expr {
+        Expr::Number(_) | Expr::Id(_) => {
+            return Value::exact(interval::eval(expr, context.ranges));
+        }
+        Expr::Neg(e) => {
+            let value = visit(e, context);
+            return Value {
+                range: value.range.neg(),
+                error: value.error,
+            };
+        }
+        Expr::Op(left, op, right) => {
+            let (l, r) = (visit(left, context), visit(right, context));
+            match op {
+                Opcode::Add => Value {
+                    range: l.range.add(&r.range),
+                    error: add(&l.error, &r.error),
+                },
+                Opcode::Sub => Value {
+                    range: l.range.sub(&r.range),
+                    error: add(&l.error, &r.error),
+                },
+                Opcode::Mul(_) => mul(&l, &r),
+                Opcode::Div(_) => div(&l, &r),
+                Opcode::Pow => pow(&l, right, &r),
+            }
+        }
+        Expr::Call(name, args, _) => {
+            let values: Vec<Value> = args.iter().map(|arg| visit(arg, context)).collect();
+            let wad = || Value::exact(Interval::point(builtins::wad()));
+            let range = interval::eval(expr, context.ranges);
+            let builtin = builtins::lookup(name).map(|builtin| builtin.semantics);
+            match builtin {
+                // The product is exact, only the division rounds
+                Some(Semantics::MulDiv) => div(&mul(&values[0], &values[1]), &values[2]),
+                Some(Semantics::MulWad) => div(&mul(&values[0], &values[1]), &wad()),
+                Some(Semantics::DivWad) => div(&mul(&values[0], &wad()), &values[1]),
+                Some(Semantics::Div) => div(&values[0], &values[1]),
+                Some(Semantics::Pow) => {
+                    let value = pow(&values[0], &args[1], &values[1]);
+                    Value {
+                        range,
+                        error: add(&value.error, &ulp()),
+                    }
+                }
+                // |√x - √x'| <= √|x - x'|, sqrtWad(x) being √(x * 1e18)
+                Some(Semantics::Sqrt | Semantics::SqrtWad) => {
+                    let error = if builtin == Some(Semantics::SqrtWad) {
+                        mul(&values[0], &wad()).error
+                    } else {
+                        values[0].error.clone()
+                    };
+                    let propagated = error.as_ref().map(|error| {
+                        if error.is_zero() {
+                            BigRational::zero()
+                        } else {
+                            BigRational::from_integer(error.ceil().to_integer().sqrt() + 1)
+                        }
+                    });
+                    Value {
+                        range,
+                        error: add(&propagated, &ulp()),
+                    }
+                }
+                // ln' = 1/x and log2' = 1/(x ln 2) < 3/(2x), lnWad(x) being ln(x / 1e18) * 1e18
+                Some(Semantics::Ln | Semantics::LnWad) => {
+                    let min = values[0].range.min_abs();
+                    let slope =
+                        (values[0].range.is_non_negative() && !min.is_zero()).then(|| min.recip());
+                    let slope = if builtin == Some(Semantics::LnWad) {
+                        slope.map(|slope| slope * builtins::wad())
+                    } else {
+                        slope
+                    };
+                    lipschitz(&values[0], range, slope)
+                }
+                Some(Semantics::Log2) => {
+                    let min = values[0].range.min_abs();
+                    let slope = (values[0].range.is_non_negative() && !min.is_zero())
+                        .then(|| BigRational::new(3.into(), 2.into()) / min);
+                    lipschitz(&values[0], range, slope)
+                }
+                Some(Semantics::Abs) => Value {
+                    range,
+                    error: values[0].error.clone(),
+                },
+                Some(Semantics::Min | Semantics::Max) => Value {
+                    range,
+                    error: max(&values[0].error, &values[1].error),
+                },
+                Some(Semantics::PowWad | Semantics::RPow | Semantics::Exp | Semantics::ExpWad) => {
+                    lipschitz(&values[0], range, None)
+                }
+                None => Value { range, error: None },
+            }
+        }
+        Expr::Cond(_, then, otherwise) => {
+            let (then, otherwise) = (visit(then, context), visit(otherwise, context));
+            return Value {
+                range: then.range.hull(&otherwise.range),
+                error: max(&then.error, &otherwise.error),
+            };
+        }
+        Expr::Error => {
+            return Value {
+                range: Interval::unbounded(),
+                error: None,
+            }
+        }
+    };
+
+    context.bounds.push(ErrorBound {
+        expression: expr.location(),
+        absolute: value.error.clone(),
+        relative: relative(&value),
+    });
+    value
+}
+
+/// Computes how far each operation of the formula can be from its exact value, from
+/// the declared ranges of the identifiers, and the bound of the final result.
+///
+/// The identifiers and literals are exact. Each division, library call and fractional
+/// `**` rounds its result by up to 1, and the errors of the operands are propagated
+/// through the later operations, e.g. multiplied by the other operand of a
+/// multiplication. The error of a conditional expression is the largest error of its
+/// branches, assuming the rounding does not change the branch taken.
+///
+/// The operations are listed in evaluation order.
+pub fn error_bounds(expr: &Expr, ranges: &Ranges) -> (Vec<ErrorBound>, ErrorBound) {
+    let mut context = Context {
+        ranges,
+        bounds: vec![],
+    };
+    let value = visit(expr, &mut context);
+    let total = ErrorBound {
+        expression: expr.location(),
+        absolute: value.error.clone(),
+        relative: relative(&value),
+    };
+    (context.bounds, total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::fixtures::ranges;
+    use crate::parser::arithmetic;
+
+    #[test]
+    fn test_approximate() {
+        let ratio = |numer: i64, denom: i64| BigRational::new(numer.into(), denom.into());
+        assert_eq!(approximate(&ratio(2, 1)), "2");
+        assert_eq!(approximate(&ratio(4, 3)), "1.334");
+        assert_eq!(approximate(&ratio(3, 2)), "1.5");
+        assert_eq!(approximate(&ratio(1, 4_000_000_000_000_000)), "2.5e-16");
+        assert_eq!(approximate(&ratio(123_456_789, 1)), "1.235e8");
+        assert_eq!(approximate(&ratio(99_999, 100_000)), "1");
+    }
+
+    #[test]
+    fn test_error_bounds() {
+        let ast = arithmetic::ExprParser::new()
+            .parse("a / b * c + sqrt(a) - a ** 2")
+            .unwrap();
+        let ranges = ranges(&[("a", "[1, 100]"), ("b", "[1, 10]"), ("c", "[1, 1e18]")]);
+        let (bounds, total) = error_bounds(&ast, &ranges);
+        let bounds: Vec<String> = bounds.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            bounds,
+            vec![
+                "a / b: ±1 (relative ±10)",
+                "(a / b) * c: ±1e18 (relative ±1e19)",
+                "sqrt(a): ±1 (relative ±1)",
+                "((a / b) * c) + sqrt(a): ±1.001e18 (relative ±9.091e17)",
+                "a ** 2: ±0 (relative ±0)",
+                "(((a / b) * c) + sqrt(a)) - (a ** 2): ±1.001e18",
+            ]
+        );
+        assert_eq!(
+            total.to_string(),
+            "(((a / b) * c) + sqrt(a)) - (a ** 2): ±1.001e18"
+        );
+
+        let ast = arithmetic::ExprParser::new()
+            .parse("(a / b) ** 2 + exp(a / b)")
+            .unwrap();
+        let (bounds, total) = error_bounds(&ast, &ranges);
+        assert_eq!(bounds[1].to_string(), "(a / b) ** 2: ±201");
+        assert_eq!(total.absolute, None);
+
+        // The integer exponentiation is exact, unless the exponent can be negative
+        let ast = arithmetic::ExprParser::new()
+            .parse("a ** b + b ** (1 - a)")
+            .unwrap();
+        let (bounds, _) = error_bounds(&ast, &ranges);
+        let bounds: Vec<String> = bounds.iter().map(ToString::to_string).collect();
+        assert_eq!(bounds[0], "a ** b: ±0 (relative ±0)");
+        assert_eq!(bounds[2], "b ** (1 - a): ±1");
+    }
+}
@@ -0,0 +1,389 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Result};
+use clap::ValueEnum;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+use super::ast::{Comparison, Expr, Literal, Opcode, Rounding, MAX_EXPONENT};
+use super::builtins::{self, Semantics};
+
+/// The values of the identifiers.
+pub type Values = BTreeMap<String, BigRational>;
+
+/// How the operations round their results.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Mode {
+    /// Computes the exact rational values, without rounding
+    Exact,
+    /// Every operation rounds down
+    Floor,
+    /// Every operation rounds up
+    Ceil,
+    /// Every operation truncates its result, as the Solidity integer division does
+    TowardZero,
+    /// Every operation rounds in its direction: the arrows of the annotated operations,
+    /// the directions of the library calls, and truncation otherwise
+    Annotated,
+}
+
+impl Mode {
+    /// Resolves the `Annotated` mode for an operation rounding in `direction`.
+    fn resolve(self, direction: Rounding) -> Mode {
+        match (self, direction) {
+            (Mode::Annotated, Rounding::Up) => Mode::Ceil,
+            (Mode::Annotated, Rounding::Down) => Mode::Floor,
+            (Mode::Annotated, _) => Mode::TowardZero,
+            (mode, _) => mode,
+        }
+    }
+
+    /// Rounds a value to an integer, `Exact` keeps it unchanged.
+    fn round(self, value: BigRational, direction: Rounding) -> BigRational {
+        match self.resolve(direction) {
+            Mode::Floor => value.floor(),
+            Mode::Ceil => value.ceil(),
+            Mode::TowardZero => value.trunc(),
+            Mode::Exact | Mode::Annotated => value,
+        }
+    }
+}
+
+/// Parses a `name=value` assignment, the value being written as a literal of the
+/// formulas (`1e18`, `0.5`, `0xff`), optionally negative.
+pub fn parse_value(assignment: &str) -> Result<(String, BigRational)> {
+    let (name, value) = assignment
+        .split_once('=')
+        .ok_or_else(|| anyhow!("Invalid value {assignment}, expected name=value"))?;
+    let value = value.trim();
+    let (negative, digits) = match value.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, value),
+    };
+    let literal =
+        Literal::from_str(digits).map_err(|e| anyhow!("Invalid value {assignment}: {e}"))?;
+    let value = if negative {
+        -literal.value
+    } else {
+        literal.value
+    };
+    Ok((name.trim().to_string(), value))
+}
+
+/// `base ** exponent` for an integer exponent.
+fn pow(expr: &Expr, base: &BigRational, exponent: &BigRational) -> Result<BigRational> {
+    if !exponent.is_integer() {
+        bail!("The exponent of {expr} is {exponent}, the result can not be evaluated exactly");
+    }
+    let magnitude = exponent
+        .to_integer()
+        .abs()
+        .to_u32()
+        .filter(|magnitude| *magnitude <= MAX_EXPONENT)
+        .ok_or_else(|| anyhow!("The exponent of {expr} is {exponent}, it is too large"))?;
+    let power = num_traits::pow(base.clone(), magnitude as usize);
+    if exponent.is_negative() {
+        if power.is_zero() {
+            bail!("Division by zero in {expr}");
+        }
+        Ok(power.recip())
+    } else {
+        Ok(power)
+    }
+}
+
+fn div(expr: &Expr, numerator: BigRational, denominator: &BigRational) -> Result<BigRational> {
+    if denominator.is_zero() {
+        bail!("Division by zero in {expr}");
+    }
+    Ok(numerator / denominator)
+}
+
+/// `√x`, rounded to an integer unless the mode is `Exact`.
+fn sqrt(expr: &Expr, x: &BigRational, mode: Mode) -> Result<BigRational> {
+    if x.is_negative() {
+        bail!("{expr} is the square root of the negative value {x}");
+    }
+    let floor = BigRational::from_integer(x.floor().to_integer().sqrt());
+    match mode {
+        Mode::Floor | Mode::TowardZero => Ok(floor),
+        Mode::Ceil if floor.pow(2) == *x => Ok(floor),
+        Mode::Ceil => Ok(floor + BigRational::one()),
+        Mode::Exact | Mode::Annotated => {
+            let (numer, denom) = (x.numer().sqrt(), x.denom().sqrt());
+            let root = BigRational::new(numer, denom);
+            if root.pow(2) == *x {
+                Ok(root)
+            } else {
+                bail!("{expr} is irrational, it can not be evaluated exactly")
+            }
+        }
+    }
+}
+
+/// `⌊log2(x)⌋` for a positive `x`, and whether `x` is a power of 2.
+fn floor_log2(x: &BigRational) -> (i64, bool) {
+    if *x >= BigRational::one() {
+        let floor = x.floor().to_integer();
+        let log = i64::try_from(floor.bits()).unwrap_or(i64::MAX) - 1;
+        let power = BigRational::from_integer(BigInt::one() << (floor.bits() - 1));
+        (log, power == *x)
+    } else {
+        // log2(x) = -log2(1 / x), with 1 / x above 1
+        let (log, exact) = floor_log2(&x.recip());
+        if exact {
+            (-log, true)
+        } else {
+            (-log - 1, false)
+        }
+    }
+}
+
+/// `log2(x)`, rounded to an integer unless the mode is `Exact`.
+fn log2(expr: &Expr, x: &BigRational, mode: Mode) -> Result<BigRational> {
+    if !x.is_positive() {
+        bail!("{expr} is the logarithm of the non-positive value {x}");
+    }
+    let (floor, exact) = floor_log2(x);
+    let log = match mode {
+        _ if exact => floor,
+        Mode::Floor => floor,
+        Mode::TowardZero if floor >= 0 => floor,
+        Mode::Ceil | Mode::TowardZero => floor + 1,
+        Mode::Exact | Mode::Annotated => {
+            bail!("{expr} is irrational, it can not be evaluated exactly")
+        }
+    };
+    Ok(BigRational::from_integer(log.into()))
+}
+
+fn compare(comparison: Comparison, left: &BigRational, right: &BigRational) -> bool {
+    match comparison {
+        Comparison::Lt => left < right,
+        Comparison::Le => left <= right,
+        Comparison::Gt => left > right,
+        Comparison::Ge => left >= right,
+        Comparison::Eq => left == right,
+        Comparison::Ne => left != right,
+    }
+}
+
+/// Evaluates an expression for the given values of its identifiers.
+///
+/// The additions, subtractions and negations are exact. With a mode other than `Exact`,
+/// the multiplications, divisions, `**` and library calls round their results to
+/// integers as the mode prescribes. The functions with irrational results (`ln`,
+/// `exp`, `**` with a fractional exponent) can not be evaluated and return an error,
+/// as do the divisions by zero. Only the branch taken by a conditional expression is
+/// evaluated.
+pub fn eval(expr: &Expr, values: &Values, mode: Mode) -> Result<BigRational> {
+    match expr {
+        Expr::Number(n) => Ok(n.value.clone()),
+        Expr::Id(name) => values
+            .get(name)
+            .cloned()
+            .ok_or_else(|| anyhow!("No value given for {name}")),
+        Expr::Neg(e) => Ok(-eval(e, values, mode)?),
+        Expr::Op(left, op, right) => {
+            let (l, r) = (eval(left, values, mode)?, eval(right, values, mode)?);
+            match op {
+                Opcode::Add => Ok(l + r),
+                Opcode::Sub => Ok(l - r),
+                Opcode::Mul(direction) => Ok(mode.round(l * r, *direction.borrow())),
+                Opcode::Div(direction) => Ok(mode.round(div(expr, l, &r)?, *direction.borrow())),
+                Opcode::Pow => Ok(mode.round(pow(expr, &l, &r)?, Rounding::Init)),
+            }
+        }
+        Expr::Call(name, args, direction) => {
+            let builtin =
+                builtins::lookup(name).ok_or_else(|| anyhow!("Unknown function {name}"))?;
+            let args = args
+                .iter()
+                .map(|arg| eval(arg, values, mode))
+                .collect::<Result<Vec<_>>>()?;
+            // The direction determined by the analysis, or the one of the library
+            let direction = match *direction.borrow() {
+                Rounding::Up => Rounding::Up,
+                Rounding::Down => Rounding::Down,
+                _ => builtin.rounding.unwrap_or(Rounding::Init),
+            };
+            let wad = builtins::wad();
+            let value = match builtin.semantics {
+                Semantics::MulDiv => div(expr, &args[0] * &args[1], &args[2])?,
+                Semantics::MulWad => &args[0] * &args[1] / wad,
+                Semantics::DivWad => div(expr, &args[0] * wad, &args[1])?,
+                Semantics::Div => div(expr, args[0].clone(), &args[1])?,
+                Semantics::Pow => pow(expr, &args[0], &args[1])?,
+                Semantics::PowWad => pow(expr, &(&args[0] / &wad), &(&args[1] / &wad))? * wad,
+                Semantics::RPow => {
+                    let scaled = div(expr, args[0].clone(), &args[2])?;
+                    pow(expr, &scaled, &args[1])? * &args[2]
+                }
+                Semantics::Sqrt => return sqrt(expr, &args[0], mode.resolve(direction)),
+                Semantics::SqrtWad => {
+                    return sqrt(expr, &(&args[0] * wad), mode.resolve(direction))
+                }
+                Semantics::Log2 => return log2(expr, &args[0], mode.resolve(direction)),
+                Semantics::Ln if args[0].is_one() => BigRational::zero(),
+                Semantics::LnWad if args[0] == wad => BigRational::zero(),
+                Semantics::Exp if args[0].is_zero() => BigRational::one(),
+                Semantics::ExpWad if args[0].is_zero() => wad,
+                Semantics::Ln | Semantics::LnWad | Semantics::Exp | Semantics::ExpWad => {
+                    bail!("{expr} is irrational, it can not be evaluated exactly")
+                }
+                Semantics::Abs => return Ok(args[0].abs()),
+                Semantics::Min => return Ok(args[0].clone().min(args[1].clone())),
+                Semantics::Max => return Ok(args[0].clone().max(args[1].clone())),
+            };
+            Ok(mode.round(value, direction))
+        }
+        Expr::Cond(condition, then, otherwise) => {
+            let left = eval(&condition.left, values, mode)?;
+            let right = eval(&condition.right, values, mode)?;
+            if compare(condition.comparison, &left, &right) {
+                eval(then, values, mode)
+            } else {
+                eval(otherwise, values, mode)
+            }
+        }
+        Expr::Error => bail!("The formula contains an error"),
+    }
+}
+
+/// Finds a `**` whose result is not an integer for the given values, its operands
+/// rounding as in the `Annotated` mode. The analysis does not give a direction to `**`,
+/// so the truncation of such a result is not a rounding it recommends. Returns why.
+pub fn inexact_pow(expr: &Expr, values: &Values) -> Option<String> {
+    match expr {
+        Expr::Number(_) | Expr::Id(_) | Expr::Error => None,
+        Expr::Neg(e) => inexact_pow(e, values),
+        Expr::Op(left, op, right) => {
+            let inexact = inexact_pow(left, values).or_else(|| inexact_pow(right, values));
+            if inexact.is_some() || *op != Opcode::Pow {
+                return inexact;
+            }
+            let l = eval(left, values, Mode::Annotated).ok()?;
+            let r = eval(right, values, Mode::Annotated).ok()?;
+            let power = pow(expr, &l, &r).ok()?;
+            (!power.is_integer()).then(|| {
+                format!(
+                    "{} is {power}, the rounding of ** is not analyzed",
+                    expr.location()
+                )
+            })
+        }
+        Expr::Call(_, args, _) => args.iter().find_map(|arg| inexact_pow(arg, values)),
+        Expr::Cond(condition, then, otherwise) => {
+            let left = eval(&condition.left, values, Mode::Annotated).ok()?;
+            let right = eval(&condition.right, values, Mode::Annotated).ok()?;
+            inexact_pow(&condition.left, values)
+                .or_else(|| inexact_pow(&condition.right, values))
+                .or_else(|| {
+                    if compare(condition.comparison, &left, &right) {
+                        inexact_pow(then, values)
+                    } else {
+                        inexact_pow(otherwise, values)
+                    }
+                })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::arithmetic;
+
+    fn values(values: &[&str]) -> Values {
+        values
+            .iter()
+            .map(|assignment| parse_value(assignment).unwrap())
+            .collect()
+    }
+
+    fn eval_str(formula: &str, mode: Mode) -> Result<String> {
+        let ast = arithmetic::ExprParser::new().parse(formula).unwrap();
+        let values = values(&["a=10", "b=3", "c=-7", "w=0.5e18", "x=1.5"]);
+        eval(&ast, &values, mode).map(|value| value.to_string())
+    }
+
+    #[test]
+    fn test_parse_value() {
+        let (name, value) = parse_value("amount = -2.5e3").unwrap();
+        assert_eq!(name, "amount");
+        assert_eq!(value, BigRational::from_integer((-2500).into()));
+        assert!(parse_value("amount").is_err());
+        assert!(parse_value("amount=abc").is_err());
+    }
+
+    #[test]
+    fn test_eval() {
+        assert_eq!(eval_str("a * b / 7", Mode::Exact).unwrap(), "30/7");
+        assert_eq!(eval_str("a * b / 7", Mode::Floor).unwrap(), "4");
+        assert_eq!(eval_str("a * b / 7", Mode::Ceil).unwrap(), "5");
+        assert_eq!(eval_str("c / b", Mode::Floor).unwrap(), "-3");
+        assert_eq!(eval_str("c / b", Mode::TowardZero).unwrap(), "-2");
+        assert_eq!(
+            eval_str("a /↑ b + a /↓ b + a / b", Mode::Annotated).unwrap(),
+            "10"
+        );
+        assert_eq!(
+            eval_str("mulDivUp(a, a, b)", Mode::Annotated).unwrap(),
+            "34"
+        );
+        assert_eq!(eval_str("mulWadDown(a, w)", Mode::Ceil).unwrap(), "5");
+        assert_eq!(eval_str("b ** -2", Mode::Exact).unwrap(), "1/9");
+        assert_eq!(eval_str("sqrt(a)", Mode::Floor).unwrap(), "3");
+        assert_eq!(eval_str("sqrt(a)", Mode::Ceil).unwrap(), "4");
+        assert_eq!(eval_str("sqrt(x * 6)", Mode::Exact).unwrap(), "3");
+        assert_eq!(eval_str("log2(a)", Mode::Ceil).unwrap(), "4");
+        assert_eq!(eval_str("log2(0.1)", Mode::TowardZero).unwrap(), "-3");
+        assert_eq!(eval_str("a > b ? a - b : b / 0", Mode::Exact).unwrap(), "7");
+        assert_eq!(eval_str("min(abs(c), x)", Mode::Exact).unwrap(), "3/2");
+        // The WAD functions scale their arguments and results by 1e18
+        assert_eq!(
+            eval_str("sqrtWad(4e18)", Mode::Exact).unwrap(),
+            "2000000000000000000"
+        );
+        assert_eq!(
+            eval_str("powDown(2e18, 2e18)", Mode::Annotated).unwrap(),
+            "4000000000000000000"
+        );
+        assert_eq!(
+            eval_str("rpow(1.5e27, 2, 1e27)", Mode::Exact).unwrap(),
+            "2250000000000000000000000000"
+        );
+        assert_eq!(eval_str("lnWad(1e18)", Mode::Exact).unwrap(), "0");
+        assert_eq!(
+            eval_str("expWad(0)", Mode::Exact).unwrap(),
+            "1000000000000000000"
+        );
+    }
+
+    #[test]
+    fn test_eval_errors() {
+        assert!(eval_str("a / (b - 3)", Mode::Exact).is_err());
+        assert!(eval_str("a ** x", Mode::Exact).is_err());
+        assert!(eval_str("sqrt(a)", Mode::Exact).is_err());
+        assert!(eval_str("ln(a)", Mode::Floor).is_err());
+        assert!(eval_str("powDown(2e18, 0.5e18)", Mode::Exact).is_err());
+        assert!(eval_str("a * unknown", Mode::Exact).is_err());
+    }
+
+    #[test]
+    fn test_inexact_pow() {
+        let values = values(&["a=10", "b=3"]);
+        let inexact = |formula: &str| {
+            let ast = arithmetic::ExprParser::new().parse(formula).unwrap();
+            inexact_pow(&ast, &values)
+        };
+        assert_eq!(
+            inexact("b + a ** -1").unwrap(),
+            "a ** -1 is 1/10, the rounding of ** is not analyzed"
+        );
+        assert_eq!(inexact("(a / b) ** 2 + a ** b"), None);
+        assert_eq!(inexact("a > b ? a : b ** -1"), None);
+    }
+}
@@ -0,0 +1,29 @@
+//! The fixtures shared by the tests.
+
+use super::interval::Ranges;
+use crate::FormulaConfig;
+
+/// Parses the ranges of the identifiers, e.g. `ranges(&[("a", "[0, inf)")])`.
+pub fn ranges(ranges: &[(&str, &str)]) -> Ranges {
+    ranges
+        .iter()
+        .map(|(name, range)| (name.to_string(), range.parse().unwrap()))
+        .collect()
+}
+
+/// A formula config without a name nor assumptions.
+pub fn config(formula: &str, round_up: bool) -> FormulaConfig {
+    FormulaConfig {
+        formula: formula.to_string(),
+        round_up,
+        ..Default::default()
+    }
+}
+
+/// A named formula config without assumptions.
+pub fn named_config(name: &str, formula: &str, round_up: bool) -> FormulaConfig {
+    FormulaConfig {
+        name: Some(name.to_string()),
+        ..config(formula, round_up)
+    }
+}
@@ -0,0 +1,592 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Error, Formatter};
+use std::str::FromStr;
+
+use num_rational::BigRational;
+use num_traits::{One, Zero};
+use serde::{Deserialize, Serialize};
+
+use super::ast::{Expr, Literal, Opcode};
+use super::builtins::{self, Semantics};
+
+/// A finite end of an interval.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bound {
+    pub value: BigRational,
+    /// Whether the interval contains the value.
+    pub closed: bool,
+}
+
+/// The range of values an expression can take, `None` being an unbounded end.
+///
+/// Declared in the formula config as `"[0, inf)"`, `"(0, 1]"`, `"[1e18, 2e18]"`, ...
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Interval {
+    pub low: Option<Bound>,
+    pub high: Option<Bound>,
+}
+
+/// An end of an interval as an extended real, used to multiply intervals.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum End {
+    NegInf,
+    Value(BigRational, bool),
+    PosInf,
+}
+
+impl End {
+    fn rank(&self) -> u8 {
+        match self {
+            End::NegInf => 0,
+            End::Value(..) => 1,
+            End::PosInf => 2,
+        }
+    }
+
+    fn cmp(&self, other: &End) -> Ordering {
+        match (self, other) {
+            (End::Value(a, _), End::Value(b, _)) => a.cmp(b),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+
+    fn sign(&self) -> Ordering {
+        match self {
+            End::NegInf => Ordering::Less,
+            End::Value(value, _) => value.cmp(&BigRational::zero()),
+            End::PosInf => Ordering::Greater,
+        }
+    }
+
+    // 0 * ∞ is 0: the end is only reached as a limit, the zero factor dominates
+    fn mul(&self, other: &End) -> End {
+        match (self, other) {
+            (End::Value(a, a_closed), End::Value(b, b_closed)) => {
+                let closed = (a_closed & b_closed)
+                    || (a.is_zero() && *a_closed)
+                    || (b.is_zero() && *b_closed);
+                End::Value(a * b, closed)
+            }
+            (End::Value(zero, closed), _) | (_, End::Value(zero, closed)) if zero.is_zero() => {
+                End::Value(BigRational::zero(), *closed)
+            }
+            _ => {
+                if self.sign() == other.sign() {
+                    End::PosInf
+                } else {
+                    End::NegInf
+                }
+            }
+        }
+    }
+
+    fn into_bound(self) -> Option<Bound> {
+        match self {
+            End::Value(value, closed) => Some(Bound { value, closed }),
+            End::NegInf | End::PosInf => None,
+        }
+    }
+}
+
+impl Bound {
+    fn closed(value: BigRational) -> Bound {
+        Bound {
+            value,
+            closed: true,
+        }
+    }
+
+    fn open(value: BigRational) -> Bound {
+        Bound {
+            value,
+            closed: false,
+        }
+    }
+}
+
+impl Interval {
+    /// The interval of all the values.
+    pub fn unbounded() -> Interval {
+        Interval {
+            low: None,
+            high: None,
+        }
+    }
+
+    pub fn point(value: BigRational) -> Interval {
+        Interval {
+            low: Some(Bound::closed(value.clone())),
+            high: Some(Bound::closed(value)),
+        }
+    }
+
+    fn integers(low: i32, high: Option<i32>) -> Interval {
+        Interval {
+            low: Some(Bound::closed(BigRational::from_integer(low.into()))),
+            high: high.map(|high| Bound::closed(BigRational::from_integer(high.into()))),
+        }
+    }
+
+    /// Whether all the values are greater than or equal to `value`.
+    pub fn is_at_least(&self, value: &BigRational) -> bool {
+        self.low.as_ref().is_some_and(|low| low.value >= *value)
+    }
+
+    /// Whether all the values are less than or equal to `value`.
+    pub fn is_at_most(&self, value: &BigRational) -> bool {
+        self.high.as_ref().is_some_and(|high| high.value <= *value)
+    }
+
+    /// Whether all the values are greater than or equal to 0.
+    pub fn is_non_negative(&self) -> bool {
+        self.is_at_least(&BigRational::zero())
+    }
+
+    /// Places the values relative to 1, as asked for the `**` bases: `Some(true)` if they
+    /// are all greater than or equal to 1, `Some(false)` if they are all less than or
+    /// equal to 1, `None` if the interval straddles 1.
+    pub fn compare_to_one(&self) -> Option<bool> {
+        self.compare_to(&Interval::point(BigRational::one()))
+    }
+
+    /// Whether all the values are at least all the values of `other` (`Some(true)`), or
+    /// at most all of them (`Some(false)`), e.g. a WAD base compared to `1e18`.
+    pub fn compare_to(&self, other: &Interval) -> Option<bool> {
+        match (&other.low, &other.high) {
+            (_, Some(high)) if self.is_at_least(&high.value) => Some(true),
+            (Some(low), _) if self.is_at_most(&low.value) => Some(false),
+            _ => None,
+        }
+    }
+
+    /// The largest absolute value, `None` if the interval is unbounded.
+    pub fn max_abs(&self) -> Option<BigRational> {
+        self.abs().high.map(|high| high.value)
+    }
+
+    /// The smallest absolute value, 0 if the interval contains 0.
+    pub fn min_abs(&self) -> BigRational {
+        self.abs()
+            .low
+            .map_or_else(BigRational::zero, |low| low.value)
+    }
+
+    fn ends(&self) -> (End, End) {
+        let low = match &self.low {
+            Some(low) => End::Value(low.value.clone(), low.closed),
+            None => End::NegInf,
+        };
+        let high = match &self.high {
+            Some(high) => End::Value(high.value.clone(), high.closed),
+            None => End::PosInf,
+        };
+        (low, high)
+    }
+
+    pub fn add(&self, other: &Interval) -> Interval {
+        let add = |a: &Option<Bound>, b: &Option<Bound>| match (a, b) {
+            (Some(a), Some(b)) => Some(Bound {
+                value: &a.value + &b.value,
+                closed: a.closed && b.closed,
+            }),
+            _ => None,
+        };
+        Interval {
+            low: add(&self.low, &other.low),
+            high: add(&self.high, &other.high),
+        }
+    }
+
+    pub fn neg(&self) -> Interval {
+        let neg = |bound: &Option<Bound>| {
+            bound.as_ref().map(|bound| Bound {
+                value: -&bound.value,
+                closed: bound.closed,
+            })
+        };
+        Interval {
+            low: neg(&self.high),
+            high: neg(&self.low),
+        }
+    }
+
+    pub fn sub(&self, other: &Interval) -> Interval {
+        self.add(&other.neg())
+    }
+
+    pub fn mul(&self, other: &Interval) -> Interval {
+        let (a_low, a_high) = self.ends();
+        let (b_low, b_high) = other.ends();
+        let products = [
+            a_low.mul(&b_low),
+            a_low.mul(&b_high),
+            a_high.mul(&b_low),
+            a_high.mul(&b_high),
+        ];
+
+        // An extreme reached by several products is closed if any of them is
+        let extreme = |ordering: Ordering| {
+            let mut extreme = products[0].clone();
+            for product in &products[1..] {
+                match (product.cmp(&extreme), product, &extreme) {
+                    (o, _, _) if o == ordering => extreme = product.clone(),
+                    (Ordering::Equal, End::Value(value, true), End::Value(..)) => {
+                        extreme = End::Value(value.clone(), true);
+                    }
+                    _ => (),
+                }
+            }
+            extreme
+        };
+        Interval {
+            low: extreme(Ordering::Less).into_bound(),
+            high: extreme(Ordering::Greater).into_bound(),
+        }
+    }
+
+    /// The interval of `1 / x`. The divisor being zero reverts, so zero is excluded.
+    fn reciprocal(&self) -> Interval {
+        let zero = BigRational::zero();
+        if self.is_at_least(&zero) {
+            match &self.high {
+                Some(high) if high.value.is_zero() => Interval::unbounded(),
+                high => Interval {
+                    low: Some(match high {
+                        Some(high) => Bound {
+                            value: high.value.recip(),
+                            closed: high.closed,
+                        },
+                        None => Bound::open(zero),
+                    }),
+                    high: self.low.as_ref().and_then(|low| {
+                        (!low.value.is_zero()).then(|| Bound {
+                            value: low.value.recip(),
+                            closed: low.closed,
+                        })
+                    }),
+                },
+            }
+        } else if self.is_at_most(&zero) {
+            self.neg().reciprocal().neg()
+        } else {
+            Interval::unbounded()
+        }
+    }
+
+    pub fn div(&self, other: &Interval) -> Interval {
+        self.mul(&other.reciprocal())
+    }
+
+    /// The interval of `self ** exponent`, only bounded for non-negative bases.
+    pub fn pow(&self, exponent: &Interval) -> Interval {
+        let one = BigRational::one();
+        if !self.is_non_negative() {
+            Interval::unbounded()
+        } else if exponent.is_non_negative() && self.is_at_least(&one) {
+            Interval::integers(1, None)
+        } else if exponent.is_non_negative() && self.is_at_most(&one) {
+            Interval::integers(0, Some(1))
+        } else {
+            Interval::integers(0, None)
+        }
+    }
+
+    /// The smallest interval containing both intervals.
+    pub fn hull(&self, other: &Interval) -> Interval {
+        let low = match (&self.low, &other.low) {
+            (Some(a), Some(b)) => Some(if a.value <= b.value { a } else { b }.clone()),
+            _ => None,
+        };
+        let high = match (&self.high, &other.high) {
+            (Some(a), Some(b)) => Some(if a.value >= b.value { a } else { b }.clone()),
+            _ => None,
+        };
+        Interval { low, high }
+    }
+
+    fn min(&self, other: &Interval) -> Interval {
+        self.neg().max(&other.neg()).neg()
+    }
+
+    fn max(&self, other: &Interval) -> Interval {
+        let low = match (&self.low, &other.low) {
+            (Some(a), Some(b)) => Some(if a.value >= b.value { a } else { b }.clone()),
+            (Some(a), None) | (None, Some(a)) => Some(a.clone()),
+            (None, None) => None,
+        };
+        let high = match (&self.high, &other.high) {
+            (Some(a), Some(b)) => Some(if a.value >= b.value { a } else { b }.clone()),
+            _ => None,
+        };
+        Interval { low, high }
+    }
+
+    fn abs(&self) -> Interval {
+        let zero = BigRational::zero();
+        if self.is_at_least(&zero) {
+            self.clone()
+        } else if self.is_at_most(&zero) {
+            self.neg()
+        } else {
+            Interval {
+                low: Some(Bound::closed(zero)),
+                high: self.neg().high.zip(self.high.clone()).map(|(a, b)| {
+                    if a.value >= b.value {
+                        a
+                    } else {
+                        b
+                    }
+                }),
+            }
+        }
+    }
+
+    /// Bounds an increasing function `f` from its values at the `points` (e.g. `f(1) = 0`
+    /// for ln): the function stays above `f(p)` after `p` and below it before.
+    fn increasing(&self, points: &[(i32, Bound)], below: Option<Bound>) -> Interval {
+        let low = points
+            .iter()
+            .rev()
+            .find(|(point, _)| self.is_at_least(&BigRational::from_integer((*point).into())))
+            .map(|(_, value)| value.clone())
+            .or(below);
+        let high = points
+            .iter()
+            .find(|(point, _)| self.is_at_most(&BigRational::from_integer((*point).into())))
+            .map(|(_, value)| value.clone());
+        Interval { low, high }
+    }
+}
+
+impl Display for Interval {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        match &self.low {
+            Some(low) => write!(fmt, "{}{}", if low.closed { "[" } else { "(" }, low.value)?,
+            None => write!(fmt, "(-inf")?,
+        }
+        match &self.high {
+            Some(high) => write!(
+                fmt,
+                ", {}{}",
+                high.value,
+                if high.closed { "]" } else { ")" }
+            ),
+            None => write!(fmt, ", inf)"),
+        }
+    }
+}
+
+impl FromStr for Interval {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let error = || format!("Invalid range {text}, expected e.g. \"[0, inf)\"");
+
+        let text = text.trim();
+        let (low_closed, high_closed) = match (text.chars().next(), text.chars().last()) {
+            (Some(low @ ('[' | '(')), Some(high @ (']' | ')'))) => (low == '[', high == ']'),
+            _ => return Err(error()),
+        };
+        let (low, high) = text[1..text.len() - 1].split_once(',').ok_or_else(error)?;
+
+        let parse = |value: &str, closed: bool| -> Result<Option<Bound>, String> {
+            let value = value.trim();
+            let (negative, digits) = match value.strip_prefix('-') {
+                Some(digits) => (true, digits.trim()),
+                None => (false, value.strip_prefix('+').unwrap_or(value)),
+            };
+            if matches!(digits, "inf" | "∞") {
+                return Ok(None);
+            }
+            let literal = |digits: &str| Literal::from_str(digits.trim()).map_err(|_| error());
+            // Fractions, as displayed for the computed intervals
+            let value = match digits.split_once('/') {
+                Some((numerator, denominator)) => {
+                    let denominator = literal(denominator)?.value;
+                    if denominator.is_zero() {
+                        return Err(error());
+                    }
+                    literal(numerator)?.value / denominator
+                }
+                None => literal(digits)?.value,
+            };
+            let value = if negative { -value } else { value };
+            Ok(Some(Bound { value, closed }))
+        };
+
+        let interval = Interval {
+            low: parse(low, low_closed)?,
+            high: parse(high, high_closed)?,
+        };
+        if let (Some(low), Some(high)) = (&interval.low, &interval.high) {
+            if low.value > high.value {
+                return Err(error());
+            }
+        }
+        Ok(interval)
+    }
+}
+
+impl TryFrom<String> for Interval {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        text.parse()
+    }
+}
+
+impl From<Interval> for String {
+    fn from(interval: Interval) -> Self {
+        interval.to_string()
+    }
+}
+
+/// The ranges declared for the identifiers.
+pub type Ranges = BTreeMap<String, Interval>;
+
+/// `x / (x + y)` is in [0, 1] and `(x + y) / x` is at least 1 for non-negative `x` and
+/// `y`, which the interval arithmetic cannot see as it loses the dependency between the
+/// occurrences of `x`.
+fn ratio_pattern(numerator: &Expr, denominator: &Expr, ranges: &Ranges) -> Option<Interval> {
+    let is_part_of_sum = |part: &Expr, sum: &Expr| match sum {
+        Expr::Op(left, Opcode::Add, right) if **left == *part => {
+            eval(part, ranges).is_non_negative() && eval(right, ranges).is_non_negative()
+        }
+        Expr::Op(left, Opcode::Add, right) if **right == *part => {
+            eval(part, ranges).is_non_negative() && eval(left, ranges).is_non_negative()
+        }
+        _ => false,
+    };
+
+    if is_part_of_sum(numerator, denominator) {
+        Some(Interval::integers(0, Some(1)))
+    } else if is_part_of_sum(denominator, numerator) {
+        Some(Interval::integers(1, None))
+    } else {
+        None
+    }
+}
+
+/// Computes the range of values of an expression from the ranges of its identifiers.
+///
+/// The identifiers without a declared range are unbounded. The operations are evaluated
+/// exactly, without rounding.
+pub fn eval(expr: &Expr, ranges: &Ranges) -> Interval {
+    match expr {
+        Expr::Number(n) => Interval::point(n.value.clone()),
+        Expr::Id(name) => ranges
+            .get(name)
+            .cloned()
+            .unwrap_or_else(Interval::unbounded),
+        Expr::Neg(e) => eval(e, ranges).neg(),
+        Expr::Op(left, op, right) => {
+            let (l, r) = (eval(left, ranges), eval(right, ranges));
+            match op {
+                Opcode::Add => l.add(&r),
+                Opcode::Sub => l.sub(&r),
+                Opcode::Mul(_) => l.mul(&r),
+                Opcode::Div(_) => ratio_pattern(left, right, ranges).unwrap_or_else(|| l.div(&r)),
+                Opcode::Pow => l.pow(&r),
+            }
+        }
+        Expr::Call(name, args, _) => {
+            let Some(builtin) = builtins::lookup(name) else {
+                return Interval::unbounded();
+            };
+            let args: Vec<Interval> = args.iter().map(|arg| eval(arg, ranges)).collect();
+            let wad = Interval::point(builtins::wad());
+            let one = || Bound::closed(BigRational::one());
+            let zero = || Bound::closed(BigRational::zero());
+            let sqrt = |x: &Interval| x.increasing(&[(0, zero()), (1, one())], None);
+            let exp =
+                |x: &Interval| x.increasing(&[(0, one())], Some(Bound::open(BigRational::zero())));
+            let ln = |x: &Interval| x.increasing(&[(1, zero())], None);
+            match builtin.semantics {
+                Semantics::MulDiv => args[0].mul(&args[1]).div(&args[2]),
+                Semantics::MulWad => args[0].mul(&args[1]).div(&wad),
+                Semantics::DivWad => args[0].mul(&wad).div(&args[1]),
+                Semantics::Div => args[0].div(&args[1]),
+                Semantics::Pow => args[0].pow(&args[1]),
+                Semantics::PowWad => args[0].div(&wad).pow(&args[1].div(&wad)).mul(&wad),
+                Semantics::RPow => args[0].div(&args[2]).pow(&args[1]).mul(&args[2]),
+                Semantics::Sqrt => sqrt(&args[0]),
+                Semantics::SqrtWad => sqrt(&args[0].div(&wad)).mul(&wad),
+                Semantics::Exp => exp(&args[0]),
+                Semantics::ExpWad => exp(&args[0].div(&wad)).mul(&wad),
+                Semantics::Ln | Semantics::Log2 => ln(&args[0]),
+                Semantics::LnWad => ln(&args[0].div(&wad)).mul(&wad),
+                Semantics::Abs => args[0].abs(),
+                Semantics::Min => args[0].min(&args[1]),
+                Semantics::Max => args[0].max(&args[1]),
+            }
+        }
+        Expr::Cond(_, then, otherwise) => eval(then, ranges).hull(&eval(otherwise, ranges)),
+        Expr::Error => Interval::unbounded(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::fixtures::ranges;
+    use crate::parser::arithmetic;
+
+    fn eval_str(formula: &str, ranges: &Ranges) -> String {
+        let ast = arithmetic::ExprParser::new().parse(formula).unwrap();
+        eval(&ast, ranges).to_string()
+    }
+
+    #[test]
+    fn test_interval_from_str() {
+        let interval: Interval = "[0, inf)".parse().unwrap();
+        assert_eq!(interval.to_string(), "[0, inf)");
+        let interval: Interval = "( -1.5 , 1e18]".parse().unwrap();
+        assert_eq!(interval.to_string(), "(-3/2, 1000000000000000000]");
+        assert_eq!(interval.to_string().parse::<Interval>().unwrap(), interval);
+        assert_eq!(
+            "(-inf, inf)".parse::<Interval>().unwrap(),
+            Interval::unbounded()
+        );
+        assert!("[1, 0]".parse::<Interval>().is_err());
+        assert!("0, 1".parse::<Interval>().is_err());
+        assert!("[a, 1]".parse::<Interval>().is_err());
+    }
+
+    #[test]
+    fn test_eval() {
+        let wad = ranges(&[("w", "[0, 1e18]")]);
+        assert_eq!(eval_str("sqrtWad(w)", &wad), "[0, 1000000000000000000]");
+        assert_eq!(
+            eval_str("powDown(w, 2e18)", &wad),
+            "[0, 1000000000000000000]"
+        );
+        assert_eq!(
+            eval_str("rpow(w, 3, 1e18)", &wad),
+            "[0, 1000000000000000000]"
+        );
+
+        let ranges = ranges(&[("a", "[0, inf)"), ("b", "(0, 2]"), ("c", "[-1, 1]")]);
+        assert_eq!(eval_str("a + b", &ranges), "(0, inf)");
+        assert_eq!(eval_str("b - c", &ranges), "(-1, 3]");
+        assert_eq!(eval_str("b * c", &ranges), "[-2, 2]");
+        assert_eq!(eval_str("1 / b", &ranges), "[1/2, inf)");
+        assert_eq!(eval_str("a / c", &ranges), "(-inf, inf)");
+        assert_eq!(eval_str("a * -b", &ranges), "(-inf, 0]");
+        assert_eq!(eval_str("b / (b + 1)", &ranges), "[0, 1]");
+        assert_eq!(eval_str("(a + b) / b", &ranges), "[1, inf)");
+        assert_eq!(eval_str("sqrt(1 + a)", &ranges), "[1, inf)");
+        assert_eq!(eval_str("abs(c)", &ranges), "[0, 1]");
+        assert_eq!(eval_str("min(a, b)", &ranges), "[0, 2]");
+        assert_eq!(eval_str("d", &ranges), "(-inf, inf)");
+    }
+
+    #[test]
+    fn test_compare_to_one() {
+        let interval = |text: &str| text.parse::<Interval>().unwrap();
+        assert_eq!(interval("[1, inf)").compare_to_one(), Some(true));
+        assert_eq!(interval("(0, 1)").compare_to_one(), Some(false));
+        assert_eq!(interval("[0, 2]").compare_to_one(), None);
+    }
+}
@@ -0,0 +1,115 @@
+use std::fmt::{Display, Error, Formatter};
+
+use num_rational::BigRational;
+
+use super::ast::{Expr, Opcode};
+use super::interval::{self, Ranges};
+
+/// A division whose result is multiplied: `(a / b) * c` truncates `a / b` by up to 1,
+/// and the multiplication scales the error up to `|c|`, while `(a * c) / b` loses
+/// less than 1.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PrecisionLoss {
+    /// The multiplication, as written in the formula.
+    pub expression: String,
+    /// The factor multiplying the result of the division.
+    pub multiplier: String,
+    /// The largest `|c|` under the declared ranges, `None` if it is unbounded.
+    pub error: Option<BigRational>,
+    /// The expression multiplying first.
+    pub suggestion: String,
+}
+
+impl Display for PrecisionLoss {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        write!(
+            fmt,
+            "{}: the division truncates before the multiplication",
+            self.expression
+        )?;
+        match &self.error {
+            Some(error) => write!(fmt, ", losing up to {error}")?,
+            None => write!(fmt, ", losing up to |{}|", self.multiplier)?,
+        }
+        write!(fmt, ", use {} instead", self.suggestion)
+    }
+}
+
+/// Finds the divisions whose results are multiplied.
+pub fn divide_before_multiply(expr: &Expr, ranges: &Ranges) -> Vec<PrecisionLoss> {
+    let mut losses = vec![];
+    visit(expr, ranges, &mut losses);
+    losses
+}
+
+/// Returns the largest absolute value of the expression, `None` if it is unbounded.
+fn max_magnitude(expr: &Expr, ranges: &Ranges) -> Option<BigRational> {
+    let interval = interval::eval(expr, ranges);
+    let low = interval.low?.value;
+    let high = interval.high?.value;
+    Some(if -&low > high { -low } else { high })
+}
+
+fn visit(expr: &Expr, ranges: &Ranges, losses: &mut Vec<PrecisionLoss>) {
+    match expr {
+        Expr::Op(left, op, right) => {
+            if let Opcode::Mul(_) = op {
+                // (a / b) * c -> (a * c) / b, c * (a / b) -> (c * a) / b
+                let suggestion = match (&**left, &**right) {
+                    (Expr::Op(a, Opcode::Div(_), b), c) => Some((format!("({a} * {c}) / {b}"), c)),
+                    (c, Expr::Op(a, Opcode::Div(_), b)) => Some((format!("({c} * {a}) / {b}"), c)),
+                    _ => None,
+                };
+                if let Some((suggestion, multiplier)) = suggestion {
+                    losses.push(PrecisionLoss {
+                        expression: expr.location(),
+                        multiplier: multiplier.to_string(),
+                        error: max_magnitude(multiplier, ranges),
+                        suggestion,
+                    });
+                }
+            }
+            visit(left, ranges, losses);
+            visit(right, ranges, losses);
+        }
+        Expr::Neg(expr) => visit(expr, ranges, losses),
+        Expr::Call(_, args, _) => {
+            for arg in args {
+                visit(arg, ranges, losses);
+            }
+        }
+        Expr::Cond(condition, then, otherwise) => {
+            visit(&condition.left, ranges, losses);
+            visit(&condition.right, ranges, losses);
+            visit(then, ranges, losses);
+            visit(otherwise, ranges, losses);
+        }
+        Expr::Number(_) | Expr::Id(_) | Expr::Error => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::fixtures::ranges;
+    use crate::parser::arithmetic;
+
+    #[test]
+    fn test_divide_before_multiply() {
+        let ast = arithmetic::ExprParser::new()
+            .parse("a / b * c + d * (e / f) + a * b / c")
+            .unwrap();
+        let ranges = ranges(&[("c", "[-1e18, 10]")]);
+        let losses: Vec<String> = divide_before_multiply(&ast, &ranges)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            losses,
+            vec![
+                "(a / b) * c: the division truncates before the multiplication, losing up to 1000000000000000000, use (a * c) / b instead",
+                "d * (e / f): the division truncates before the multiplication, losing up to |d|, use (d * e) / f instead",
+            ]
+        );
+    }
+}
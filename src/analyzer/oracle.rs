@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+
+use super::ast::Expr;
+use crate::parser::ask_yes_no;
+
+/// Answers the questions the rounding analysis cannot decide on its own.
+///
+/// The analysis first looks at the `less_than_one` and `greater_than_one` lists of the
+/// formula config, and only asks the oracle when the expression is in neither of them.
+pub trait Oracle {
+    /// Returns `Some(true)` if `expr` is greater than or equal to 1, `Some(false)` if it is
+    /// below 1, and `None` if the question is left unanswered.
+    fn is_greater_than_one(&mut self, expr: &Expr) -> Result<Option<bool>>;
+}
+
+/// Asks the questions to the user on the standard input.
+pub struct InteractiveOracle;
+
+impl Oracle for InteractiveOracle {
+    fn is_greater_than_one(&mut self, expr: &Expr) -> Result<Option<bool>> {
+        println!("Is {expr} greater than 1? Y/N (yes, no)");
+        Ok(Some(ask_yes_no()?))
+    }
+}
+
+/// Only relies on the formula config, and fails on any question it cannot answer.
+pub struct ConfigOracle;
+
+impl Oracle for ConfigOracle {
+    fn is_greater_than_one(&mut self, expr: &Expr) -> Result<Option<bool>> {
+        Err(anyhow!(
+            "No assumption provided for {expr}, add it to less_than_one or greater_than_one"
+        ))
+    }
+}
+
+/// Never asks anything: the questions are left unanswered and reported as such.
+pub struct NoPromptOracle;
+
+impl Oracle for NoPromptOracle {
+    fn is_greater_than_one(&mut self, _expr: &Expr) -> Result<Option<bool>> {
+        Ok(None)
+    }
+}
+
+/// Replays pre-recorded answers, keyed by the expression as displayed in the report.
+pub struct ScriptedOracle {
+    answers: HashMap<String, bool>,
+}
+
+impl ScriptedOracle {
+    pub fn new<I, S>(answers: I) -> ScriptedOracle
+    where
+        I: IntoIterator<Item = (S, bool)>,
+        S: Into<String>,
+    {
+        ScriptedOracle {
+            answers: answers.into_iter().map(|(k, v)| (k.into(), v)).collect(),
+        }
+    }
+}
+
+impl Oracle for ScriptedOracle {
+    fn is_greater_than_one(&mut self, expr: &Expr) -> Result<Option<bool>> {
+        let expr_str = format!("{expr}");
+        self.answers
+            .get(&expr_str)
+            .copied()
+            .map(Some)
+            .ok_or_else(|| anyhow!("No scripted answer for {expr_str}"))
+    }
+}
@@ -0,0 +1,373 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Error, Formatter};
+use std::str::FromStr;
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+use serde::{Deserialize, Serialize};
+
+use super::ast::{Expr, Literal, Opcode, MAX_EXPONENT};
+use super::builtins::{self, Semantics};
+use super::interval::{self, Ranges};
+
+/// The number of bits of the EVM words.
+const WORD_BITS: u64 = 256;
+
+/// The type of an identifier, bounding its magnitude.
+///
+/// Declared in the formula config as `uint128`, `int256`, ... or as the maximum
+/// magnitude itself, e.g. `"1e18"` for a WAD at most 1.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Type {
+    /// The type as declared.
+    pub text: String,
+    /// The largest absolute value of the type.
+    pub max: BigRational,
+}
+
+impl FromStr for Type {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let error = || format!("Invalid type {text}, expected e.g. uint128, int256 or 1e18");
+
+        let text = text.trim();
+        let width = |bits: &str| -> Result<u32, String> {
+            let bits = if bits.is_empty() { "256" } else { bits };
+            bits.parse::<u32>()
+                .ok()
+                .filter(|bits| *bits > 0 && *bits <= 256 && bits % 8 == 0)
+                .ok_or_else(error)
+        };
+
+        let max = if let Some(bits) = text.strip_prefix("uint") {
+            BigInt::from(2).pow(width(bits)?) - 1
+        } else if let Some(bits) = text.strip_prefix("int") {
+            BigInt::from(2).pow(width(bits)? - 1)
+        } else {
+            return Literal::from_str(text)
+                .map(|literal| Type {
+                    text: text.to_string(),
+                    max: literal.value,
+                })
+                .map_err(|_| error());
+        };
+        Ok(Type {
+            text: text.to_string(),
+            max: BigRational::from_integer(max),
+        })
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        write!(fmt, "{}", self.text)
+    }
+}
+
+impl TryFrom<String> for Type {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        text.parse()
+    }
+}
+
+impl From<Type> for String {
+    fn from(declared: Type) -> Self {
+        declared.text
+    }
+}
+
+/// The types declared for the identifiers.
+pub type Types = BTreeMap<String, Type>;
+
+/// The worst-case magnitude of an intermediate value of the formula.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Magnitude {
+    /// The operation computing the value, as written in the formula.
+    pub expression: String,
+    /// The largest absolute value, `None` if it is unbounded.
+    pub bound: Option<BigRational>,
+}
+
+impl Magnitude {
+    /// The number of bits of the bound: the value is below `2^bits`.
+    pub fn bits(&self) -> Option<u64> {
+        self.bound
+            .as_ref()
+            .map(|bound| bound.floor().to_integer().bits())
+    }
+
+    /// Whether the value can exceed the 256 bits of the EVM words.
+    pub fn overflows(&self) -> bool {
+        self.bits().is_none_or(|bits| bits > WORD_BITS)
+    }
+}
+
+impl Display for Magnitude {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        match self.bits() {
+            Some(bits) => write!(fmt, "{}: < 2^{bits}", self.expression)?,
+            None => write!(fmt, "{}: unbounded", self.expression)?,
+        }
+        if self.overflows() {
+            write!(fmt, ", can overflow")?;
+        }
+        Ok(())
+    }
+}
+
+struct Context<'a> {
+    types: &'a Types,
+    ranges: &'a Ranges,
+    magnitudes: Vec<Magnitude>,
+}
+
+/// The smallest absolute value of a divisor. Dividing by zero reverts, and the integers
+/// are otherwise at least 1 in absolute value.
+fn min_divisor(expr: &Expr, ranges: &Ranges) -> BigRational {
+    let interval = interval::eval(expr, ranges);
+    let zero = BigRational::zero();
+    let min = if interval.is_at_least(&zero) {
+        interval.low.map(|low| low.value)
+    } else if interval.is_at_most(&zero) {
+        interval.high.map(|high| high.value.abs())
+    } else {
+        None
+    };
+    min.filter(|min| *min > BigRational::one())
+        .unwrap_or_else(BigRational::one)
+}
+
+fn add(a: &Option<BigRational>, b: &Option<BigRational>) -> Option<BigRational> {
+    Some(a.as_ref()? + b.as_ref()?)
+}
+
+fn mul(a: &Option<BigRational>, b: &Option<BigRational>) -> Option<BigRational> {
+    Some(a.as_ref()? * b.as_ref()?)
+}
+
+fn max(a: Option<BigRational>, b: Option<BigRational>) -> Option<BigRational> {
+    let (a, b) = (a?, b?);
+    Some(if a > b { a } else { b })
+}
+
+/// `base ** exponent`, computed for the integer exponents up to `MAX_EXPONENT`.
+fn pow(base: &Option<BigRational>, exponent: &Option<BigRational>) -> Option<BigRational> {
+    let (base, exponent) = (base.as_ref()?, exponent.as_ref()?);
+    if *base <= BigRational::one() {
+        return Some(BigRational::one());
+    }
+    let exponent = exponent.ceil().to_integer().to_u32()?;
+    (exponent <= MAX_EXPONENT).then(|| num_traits::pow(base.clone(), exponent as usize))
+}
+
+fn visit(expr: &Expr, context: &mut Context) -> Option<BigRational> {
+    let magnitude = match expr {
+        Expr::Number(n) => return Some(n.value.abs()),
+        Expr::Id(name) => {
+            let declared = context.types.get(name).map(|declared| declared.max.clone());
+            let range = context
+                .ranges
+                .get(name)
+                .and_then(interval::Interval::max_abs);
+            let word = BigRational::from_integer(BigInt::from(2).pow(256) - 1);
+            return [declared, range].into_iter().flatten().min().or(Some(word));
+        }
+        Expr::Neg(e) => return visit(e, context),
+        Expr::Op(left, op, right) => {
+            let (l, r) = (visit(left, context), visit(right, context));
+            match op {
+                Opcode::Add | Opcode::Sub => add(&l, &r),
+                Opcode::Mul(_) => mul(&l, &r),
+                Opcode::Div(_) => l.map(|l| l / min_divisor(right, context.ranges)),
+                Opcode::Pow => pow(&l, &r),
+            }
+        }
+        Expr::Call(name, args, _) => {
+            let magnitudes: Vec<Option<BigRational>> =
+                args.iter().map(|arg| visit(arg, context)).collect();
+            let wad = Some(builtins::wad());
+            let semantics = builtins::lookup(name).map(|builtin| builtin.semantics);
+            match semantics {
+                // The product is computed on 512 bits, only the result has to fit
+                Some(Semantics::MulDiv) => mul(&magnitudes[0], &magnitudes[1])
+                    .map(|product| product / min_divisor(&args[2], context.ranges)),
+                // The product is computed on 256 bits before the division
+                Some(Semantics::MulWad) => {
+                    let product = mul(&magnitudes[0], &magnitudes[1]);
+                    context.magnitudes.push(Magnitude {
+                        expression: format!("{} * {} in {expr}", args[0], args[1]),
+                        bound: product.clone(),
+                    });
+                    product.map(|product| product / builtins::wad())
+                }
+                Some(Semantics::DivWad) => {
+                    let product = mul(&magnitudes[0], &wad);
+                    context.magnitudes.push(Magnitude {
+                        expression: format!("{} * 1e18 in {expr}", args[0]),
+                        bound: product.clone(),
+                    });
+                    product.map(|product| product / min_divisor(&args[1], context.ranges))
+                }
+                Some(Semantics::Div) => magnitudes[0]
+                    .clone()
+                    .map(|magnitude| magnitude / min_divisor(&args[1], context.ranges)),
+                Some(Semantics::Pow) => pow(&magnitudes[0], &magnitudes[1]),
+                Some(Semantics::PowWad) => {
+                    let unscaled = |magnitude: &Option<BigRational>| {
+                        magnitude
+                            .as_ref()
+                            .map(|magnitude| magnitude / builtins::wad())
+                    };
+                    mul(
+                        &pow(&unscaled(&magnitudes[0]), &unscaled(&magnitudes[1])),
+                        &wad,
+                    )
+                }
+                Some(Semantics::RPow) => {
+                    let scalar = min_divisor(&args[2], context.ranges);
+                    let base = magnitudes[0].as_ref().map(|magnitude| magnitude / scalar);
+                    mul(&pow(&base, &magnitudes[1]), &magnitudes[2])
+                }
+                Some(Semantics::SqrtWad) => mul(&magnitudes[0], &wad).map(|product| {
+                    BigRational::from_integer(product.ceil().to_integer().sqrt() + 1)
+                }),
+                // |ln(x / 1e18)| * 1e18 is below x above 1e18, and below
+                // ln(1e18) * 1e18 < 42e18 under 1e18
+                Some(Semantics::LnWad) => max(
+                    magnitudes[0].clone(),
+                    mul(&Some(BigRational::from_integer(42.into())), &wad),
+                ),
+                Some(Semantics::Exp | Semantics::ExpWad) => None,
+                Some(
+                    Semantics::Sqrt
+                    | Semantics::Ln
+                    | Semantics::Log2
+                    | Semantics::Abs
+                    | Semantics::Min
+                    | Semantics::Max,
+                )
+                | None => magnitudes.into_iter().reduce(max).flatten(),
+            }
+        }
+        Expr::Cond(condition, then, otherwise) => {
+            visit(&condition.left, context);
+            visit(&condition.right, context);
+            return max(visit(then, context), visit(otherwise, context));
+        }
+        Expr::Error => return None,
+    };
+
+    context.magnitudes.push(Magnitude {
+        expression: expr.location(),
+        bound: magnitude.clone(),
+    });
+    magnitude
+}
+
+/// Computes the worst-case magnitude of every operation of the formula, from the
+/// declared types and ranges of the identifiers. An identifier declaring neither is
+/// bounded by `uint256`.
+///
+/// The operations are listed in evaluation order.
+pub fn magnitudes(expr: &Expr, types: &Types, ranges: &Ranges) -> Vec<Magnitude> {
+    let mut context = Context {
+        types,
+        ranges,
+        magnitudes: vec![],
+    };
+    visit(expr, &mut context);
+    context.magnitudes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::fixtures::{self, ranges};
+    use crate::parser::arithmetic;
+
+    fn types(types: &[(&str, &str)]) -> Types {
+        types
+            .iter()
+            .map(|(name, declared)| (name.to_string(), declared.parse().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_type_from_str() {
+        let declared: Type = "uint128".parse().unwrap();
+        assert_eq!(
+            declared.max,
+            BigRational::from_integer(BigInt::from(u128::MAX))
+        );
+        let declared: Type = "int8".parse().unwrap();
+        assert_eq!(declared.max, BigRational::from_integer(128.into()));
+        let declared: Type = "1e18".parse().unwrap();
+        assert_eq!(declared.to_string(), "1e18");
+        assert!("uint7".parse::<Type>().is_err());
+        assert!("uint512".parse::<Type>().is_err());
+        assert!("address".parse::<Type>().is_err());
+    }
+
+    #[test]
+    fn test_magnitudes() {
+        let ast = arithmetic::ExprParser::new()
+            .parse("a * b / c + a * b * d + mulWadDown(w, d) + 2 ** e")
+            .unwrap();
+        let types = types(&[("a", "uint128"), ("b", "uint128"), ("w", "1e18")]);
+        let ranges = ranges(&[("e", "[0, 255]")]);
+        let bounds: Vec<String> = magnitudes(&ast, &types, &ranges)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            bounds,
+            vec![
+                "a * b: < 2^256",
+                "(a * b) / c: < 2^256",
+                "a * b: < 2^256",
+                "(a * b) * d: < 2^512, can overflow",
+                "((a * b) / c) + ((a * b) * d): < 2^512, can overflow",
+                "w * d in mulWadDown(w, d): < 2^316, can overflow",
+                "mulWadDown(w, d): < 2^256",
+                "(((a * b) / c) + ((a * b) * d)) + mulWadDown(w, d): < 2^512, can overflow",
+                "2 ** e: < 2^256",
+                "((((a * b) / c) + ((a * b) * d)) + mulWadDown(w, d)) + (2 ** e): < 2^512, can overflow",
+            ]
+        );
+
+        // The product before the division by the divisor
+        let ast = arithmetic::ExprParser::new()
+            .parse("divWadUp(w, c)")
+            .unwrap();
+        let ranges = fixtures::ranges(&[("c", "[1e18, inf)")]);
+        let bounds: Vec<String> = magnitudes(&ast, &types, &ranges)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            bounds,
+            vec![
+                "w * 1e18 in divWadUp(w, c): < 2^120",
+                "divWadUp(w, c): < 2^60"
+            ]
+        );
+
+        let ast = arithmetic::ExprParser::new().parse("a + exp(w)").unwrap();
+        let bounds: Vec<String> = magnitudes(&ast, &types, &ranges)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            bounds,
+            vec![
+                "exp(w): unbounded, can overflow",
+                "a + exp(w): unbounded, can overflow",
+            ]
+        );
+    }
+}
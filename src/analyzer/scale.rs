@@ -0,0 +1,200 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Error, Formatter};
+
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+
+use super::ast::{Expr, Literal, Opcode, Rounding};
+use super::builtins::{self, Semantics};
+
+/// The decimals declared for the identifiers, e.g. 18 for a WAD and 27 for a RAY.
+pub type Decimals = BTreeMap<String, u32>;
+
+/// The decimals of the WAD based functions, e.g. `mulWadDown`.
+const WAD_DECIMALS: i64 = 18;
+
+/// Two values with different decimals combined by an operation requiring equal decimals.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ScaleMismatch {
+    /// The operation, as written in the formula.
+    pub operation: String,
+    /// The operand with the most decimals.
+    pub operand: String,
+    /// The decimals of the operand.
+    pub from: i64,
+    /// The decimals of the other operand.
+    pub to: i64,
+    /// The direction the operand rounds to when rescaled.
+    pub rounding: Rounding,
+}
+
+impl Display for ScaleMismatch {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        write!(
+            fmt,
+            "{}: {} has {} decimals instead of {}, rescaling it divides by 1e{} rounding {}",
+            self.operation,
+            self.operand,
+            self.from,
+            self.to,
+            self.from - self.to,
+            self.rounding
+        )
+    }
+}
+
+/// The decimals of a literal written as a power of ten, `k` for `1ek` or `1Ek`: the
+/// fixed-point scales, e.g. `1e18`. The other literals, e.g. the basis points of `a * 30 / 10000`,
+/// are not scales and have unknown decimals, unless declared in `decimals` by their text.
+fn literal_decimals(literal: &Literal, decimals: &Decimals) -> Option<i64> {
+    if let Some(declared) = decimals.get(&literal.text) {
+        return Some(i64::from(*declared));
+    }
+    literal
+        .text
+        .strip_prefix("1e")
+        .or_else(|| literal.text.strip_prefix("1E"))
+        .and_then(|exponent| exponent.parse::<u32>().ok())
+        .map(i64::from)
+}
+
+/// Computes the decimals of an expression from the decimals of its identifiers: the
+/// multiplications add them, the divisions subtract them. `None` if they are unknown.
+///
+/// The operations requiring equal decimals take the decimals of their first operand
+/// with known decimals, the mismatches are reported by `mismatch`.
+pub fn eval(expr: &Expr, decimals: &Decimals) -> Option<i64> {
+    let first_known = |a: Option<i64>, b: Option<i64>| a.or(b);
+    match expr {
+        Expr::Number(n) => literal_decimals(n, decimals),
+        Expr::Id(name) => decimals.get(name).map(|decimals| i64::from(*decimals)),
+        Expr::Neg(e) => eval(e, decimals),
+        Expr::Op(left, op, right) => {
+            let (l, r) = (eval(left, decimals), eval(right, decimals));
+            match op {
+                Opcode::Add | Opcode::Sub => first_known(l, r),
+                Opcode::Mul(_) => Some(l? + r?),
+                Opcode::Div(_) => Some(l? - r?),
+                Opcode::Pow => match (&**left, &**right) {
+                    // 10 ** k is the scale of k decimals
+                    (Expr::Number(base), Expr::Number(n))
+                        if base.value == BigInt::from(10).into() && n.value.is_integer() =>
+                    {
+                        n.value.to_integer().to_i64()
+                    }
+                    _ => match (l?, &**right) {
+                        (0, _) => Some(0),
+                        (l, Expr::Number(n)) if n.value.is_integer() => {
+                            Some(l * n.value.to_integer().to_i64()?)
+                        }
+                        _ => None,
+                    },
+                },
+            }
+        }
+        Expr::Call(name, args, _) => {
+            let args: Vec<Option<i64>> = args.iter().map(|arg| eval(arg, decimals)).collect();
+            match builtins::lookup(name)?.semantics {
+                Semantics::MulDiv => Some(args[0]? + args[1]? - args[2]?),
+                Semantics::MulWad => Some(args[0]? + args[1]? - WAD_DECIMALS),
+                Semantics::DivWad => Some(args[0]? + WAD_DECIMALS - args[1]?),
+                Semantics::Div => Some(args[0]? - args[1]?),
+                // The WAD functions and rpow keep the scale of their argument
+                Semantics::PowWad | Semantics::RPow | Semantics::LnWad | Semantics::ExpWad => {
+                    args[0]
+                }
+                // The unscaled functions do not keep a fixed-point scale
+                Semantics::Pow | Semantics::Ln | Semantics::Exp | Semantics::Log2 => None,
+                Semantics::Sqrt => args[0].filter(|decimals| decimals % 2 == 0).map(|d| d / 2),
+                Semantics::SqrtWad => args[0]
+                    .map(|decimals| decimals + WAD_DECIMALS)
+                    .filter(|decimals| decimals % 2 == 0)
+                    .map(|d| d / 2),
+                Semantics::Abs => args[0],
+                Semantics::Min | Semantics::Max => first_known(args[0], args[1]),
+            }
+        }
+        Expr::Cond(_, then, otherwise) => {
+            first_known(eval(then, decimals), eval(otherwise, decimals))
+        }
+        Expr::Error => None,
+    }
+}
+
+/// Compares the decimals of two operands that must be equal. If they differ, the
+/// operand with the most decimals has to be divided, rounding as it is required to.
+pub fn mismatch(
+    operation: &str,
+    (left, left_rounding): (&Expr, Rounding),
+    (right, right_rounding): (&Expr, Rounding),
+    decimals: &Decimals,
+) -> Option<ScaleMismatch> {
+    let (l, r) = (eval(left, decimals)?, eval(right, decimals)?);
+    let (operand, rounding, from, to) = match l.cmp(&r) {
+        std::cmp::Ordering::Equal => return None,
+        std::cmp::Ordering::Greater => (left, left_rounding, l, r),
+        std::cmp::Ordering::Less => (right, right_rounding, r, l),
+    };
+    Some(ScaleMismatch {
+        operation: operation.to_string(),
+        operand: operand.to_string(),
+        from,
+        to,
+        rounding,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::arithmetic;
+
+    fn decimals() -> Decimals {
+        [("a", 18), ("b", 18), ("r", 27), ("amount", 6)]
+            .iter()
+            .map(|(name, decimals)| (name.to_string(), *decimals))
+            .collect()
+    }
+
+    fn eval_str(formula: &str) -> Option<i64> {
+        let ast = arithmetic::ExprParser::new().parse(formula).unwrap();
+        eval(&ast, &decimals())
+    }
+
+    #[test]
+    fn test_eval() {
+        assert_eq!(eval_str("a * b / 1e18"), Some(18));
+        assert_eq!(eval_str("a * r"), Some(45));
+        assert_eq!(eval_str("amount * 1e2 / a"), Some(-10));
+        assert_eq!(eval_str("a * b / 10 ** 18"), Some(18));
+        assert_eq!(eval_str("a * b / 1E18"), Some(18));
+        assert_eq!(eval_str("amount * 2 / a"), None);
+        assert_eq!(eval_str("a * 30 / 10000 + a"), Some(18));
+        assert_eq!(eval_str("mulWadDown(a, amount)"), Some(6));
+        assert_eq!(eval_str("FullMath.mulDiv(r, amount, 1e27)"), Some(6));
+        assert_eq!(eval_str("powDown(a, b)"), Some(18));
+        assert_eq!(eval_str("rpow(r, 2, 1e27)"), Some(27));
+        assert_eq!(eval_str("ln(a)"), None);
+        assert_eq!(eval_str("a * unknown"), None);
+        assert_eq!(eval_str("unknown + a"), Some(18));
+    }
+
+    #[test]
+    fn test_mismatch() {
+        let ast = arithmetic::ExprParser::new().parse("a + r").unwrap();
+        let Expr::Op(left, _, right) = &*ast else {
+            unreachable!()
+        };
+        let mismatch = mismatch(
+            "a + r",
+            (left, Rounding::Up),
+            (right, Rounding::Down),
+            &decimals(),
+        )
+        .unwrap();
+        assert_eq!(
+            mismatch.to_string(),
+            "a + r: r has 27 decimals instead of 18, rescaling it divides by 1e9 rounding down"
+        );
+    }
+}
@@ -0,0 +1,344 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Signed, Zero};
+use rand::Rng;
+
+use super::ast::{Expr, Rounding};
+use super::collect_ids;
+use super::evaluator::{self, Mode, Values};
+use super::interval::{Interval, Ranges};
+use super::verify::{self, Counterexample, Verification};
+
+/// The largest power of ten tried, `1e36` covers a WAD times a WAD.
+const MAX_POWER_OF_TEN: u32 = 36;
+
+/// The small values tried, as numerators and denominators.
+const SMALL_VALUES: &[i32] = &[0, 1, 2, 3, 7, 9, 11];
+
+/// The values of an interval most likely to expose a wrong rounding: its ends and their
+/// neighbours, small values (small denominators make large truncations), and powers of
+/// ten and their neighbours (the fixed-point scales, and the values near 1 of the `**`
+/// bases).
+///
+/// An interval without integers, e.g. `(0, 1)`, gives the middle of its ends.
+fn candidates(interval: &Interval, rng: &mut impl Rng) -> Vec<BigRational> {
+    let (low, high) = verify::integer_bounds(interval);
+    let one = BigInt::one();
+
+    let mut values = vec![low.clone(), &low + &one, &high - &one, high.clone()];
+    for small in SMALL_VALUES {
+        values.push(BigInt::from(*small));
+        values.push(-BigInt::from(*small));
+    }
+    for exponent in 1..=MAX_POWER_OF_TEN {
+        let power = BigInt::from(10).pow(exponent);
+        values.push(&power - &one);
+        values.push(&power + &one);
+        values.push(power);
+    }
+
+    values.retain(|value| low <= *value && *value <= high);
+    values.sort_unstable();
+    values.dedup();
+    if values.is_empty() {
+        return vec![verify::sample(interval, rng)];
+    }
+    values.into_iter().map(BigRational::from_integer).collect()
+}
+
+/// Searches inputs for which a formula, as implemented, rounds on the wrong side of its
+/// exact value, and returns the ones with the largest error.
+///
+/// The formula is evaluated with its operations rounding as written: the arrows of the
+/// annotated operations, the directions of the library calls, and truncation otherwise.
+/// The identifiers take the `candidates` values of their declared ranges (of `uint256`
+/// without a range). All the combinations are tried if there are at most `budget`,
+/// otherwise `budget` random combinations.
+pub fn search(
+    ast: &Expr,
+    rounding: Rounding,
+    ranges: &Ranges,
+    budget: usize,
+    rng: &mut impl Rng,
+) -> Verification {
+    let mut ids = vec![];
+    collect_ids(ast, &mut ids);
+    ids.sort_unstable();
+    ids.dedup();
+
+    let uint256 = "[0, inf)".parse::<Interval>().unwrap();
+    let candidates: Vec<Vec<BigRational>> = ids
+        .iter()
+        .map(|id| candidates(ranges.get(id).unwrap_or(&uint256), rng))
+        .collect();
+    let combinations = candidates
+        .iter()
+        .try_fold(1usize, |product, values| product.checked_mul(values.len()))
+        .filter(|combinations| *combinations <= budget);
+
+    let mut verification = Verification {
+        samples: 0,
+        skipped: 0,
+        skip_reason: None,
+        counterexample: None,
+    };
+
+    for index in 0..combinations.unwrap_or(budget) {
+        // Decodes the index of the combination, or draws one if they are too many
+        let mut rest = index;
+        let values: Values = ids
+            .iter()
+            .zip(&candidates)
+            .map(|(id, values)| {
+                let choice = if combinations.is_some() {
+                    let choice = rest % values.len();
+                    rest /= values.len();
+                    choice
+                } else {
+                    rng.gen_range(0..values.len())
+                };
+                (id.clone(), values[choice].clone())
+            })
+            .collect();
+
+        let evaluation = evaluator::eval(ast, &values, Mode::Exact).and_then(|exact| {
+            evaluator::eval(ast, &values, Mode::Annotated).map(|rounded| (exact, rounded))
+        });
+        let (exact, rounded) = match evaluation {
+            Ok(evaluation) => evaluation,
+            Err(e) => {
+                verification.skipped += 1;
+                verification.skip_reason = Some(e.to_string());
+                continue;
+            }
+        };
+        verification.samples += 1;
+
+        // Positive when the result lands on the wrong side
+        let error = match rounding {
+            Rounding::Up => &exact - &rounded,
+            Rounding::Down => &rounded - &exact,
+            Rounding::Init | Rounding::Unknown => BigRational::zero(),
+        };
+        let largest = verification
+            .counterexample
+            .as_ref()
+            .map_or_else(BigRational::zero, Counterexample::error);
+        if error.is_positive() && error > largest {
+            verification.counterexample = Some(Counterexample {
+                values,
+                exact,
+                rounded,
+                rounding,
+            });
+        }
+    }
+
+    verification
+}
+
+/// Turns an identifier into a Solidity variable name, `params.weight[i]` giving
+/// `params_weight_i_`.
+fn variable(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// Gives each identifier a distinct Solidity variable name, also distinct from the
+/// `reserved` names. A name already taken, e.g. by `a_b` for `a.b`, gets a suffix:
+/// `a_b_1`.
+fn variables(ids: impl Iterator<Item = String>, reserved: &[&str]) -> BTreeMap<String, String> {
+    let mut taken: BTreeSet<String> = reserved.iter().map(ToString::to_string).collect();
+    let mut variables = BTreeMap::new();
+    for id in ids {
+        let base = variable(&id);
+        let mut name = base.clone();
+        let mut suffix = 1;
+        while taken.contains(&name) {
+            name = format!("{base}_{suffix}");
+            suffix += 1;
+        }
+        taken.insert(name.clone());
+        variables.insert(id, name);
+    }
+    variables
+}
+
+/// The Solidity type of a value, `int256` if it is negative.
+fn solidity_type(value: &BigRational) -> &'static str {
+    if value.is_negative() {
+        "int256"
+    } else {
+        "uint256"
+    }
+}
+
+/// Writes a counterexample as a Foundry test: the inputs as Solidity variables, a call to
+/// a placeholder to replace with a call to the implementation, and an assertion that the
+/// result is on the right side of the exact value.
+///
+/// Returns `None` if an input is not an integer, e.g. drawn from `(0, 1)`, as it can not
+/// be passed to the implementation.
+pub fn foundry_test(name: &str, formula: &str, counterexample: &Counterexample) -> Option<String> {
+    if !counterexample.values.values().all(BigRational::is_integer) {
+        return None;
+    }
+    // The integer results on the right side of the exact value
+    let (assertion, bound) = match counterexample.rounding {
+        Rounding::Up => ("assertGe", counterexample.exact.ceil()),
+        Rounding::Down => ("assertLe", counterexample.exact.floor()),
+        Rounding::Init | Rounding::Unknown => return None,
+    };
+    let result_type = solidity_type(&bound);
+    let implementation = format!("{}_implementation", variable(name));
+    let variables = variables(
+        counterexample.values.keys().cloned(),
+        &["result", &implementation],
+    );
+    let parameters: Vec<String> = counterexample
+        .values
+        .iter()
+        .map(|(id, value)| format!("{} {}", solidity_type(value), variables[id]))
+        .collect();
+    let arguments: Vec<&str> = counterexample
+        .values
+        .keys()
+        .map(|id| variables[id].as_str())
+        .collect();
+
+    let mut lines = vec![format!(
+        "function test_{}_rounding() public {{",
+        variable(name)
+    )];
+    for (id, value) in &counterexample.values {
+        lines.push(format!(
+            "    {} {} = {value};",
+            solidity_type(value),
+            variables[id]
+        ));
+    }
+    lines.push(format!(
+        "    // exact: {}, computed as written: {}, it must round {}",
+        counterexample.exact, counterexample.rounded, counterexample.rounding
+    ));
+    lines.push(format!(
+        "    {result_type} result = {implementation}({});",
+        arguments.join(", ")
+    ));
+    lines.push(format!("    {assertion}(result, {bound});"));
+    lines.push("}".to_string());
+    lines.push(String::new());
+    lines.push(format!(
+        "/// Replace with a call to the implementation of {formula}"
+    ));
+    lines.push(format!(
+        "function {implementation}({}) internal returns ({result_type}) {{",
+        parameters.join(", ")
+    ));
+    lines.push("    revert(\"not implemented\");".to_string());
+    lines.push("}".to_string());
+    Some(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::fixtures::ranges;
+    use crate::parser::arithmetic;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_candidates() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let interval: Interval = "(1, 1e3]".parse().unwrap();
+        let values: Vec<String> = candidates(&interval, &mut rng)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            values,
+            vec!["2", "3", "7", "9", "10", "11", "99", "100", "101", "999", "1000"]
+        );
+    }
+
+    #[test]
+    fn test_search() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let ranges = ranges(&[("b", "[1, 1e18]")]);
+
+        let ast = arithmetic::ExprParser::new().parse("a *↓ 3 /↓ b").unwrap();
+        let verification = search(&ast, Rounding::Up, &ranges, 10_000, &mut rng);
+        let counterexample = verification.counterexample.unwrap();
+        assert!(counterexample.rounded < counterexample.exact);
+        assert!(counterexample.error() < BigRational::one());
+
+        let ast = arithmetic::ExprParser::new()
+            .parse("mulDivUp(a, 3, b)")
+            .unwrap();
+        let verification = search(&ast, Rounding::Up, &ranges, 10_000, &mut rng);
+        assert!(verification.counterexample.is_none());
+        assert!(verification.samples > 0);
+    }
+
+    #[test]
+    fn test_foundry_test() {
+        let counterexample = Counterexample {
+            values: [
+                ("self.a".to_string(), BigRational::from_integer(7.into())),
+                ("b".to_string(), BigRational::from_integer((-3).into())),
+            ]
+            .into(),
+            exact: BigRational::new((-7).into(), 3.into()),
+            rounded: BigRational::from_integer((-3).into()),
+            rounding: Rounding::Up,
+        };
+        assert_eq!(
+            foundry_test("price", "self.a /↓ b", &counterexample).unwrap(),
+            "function test_price_rounding() public {
+    int256 b = -3;
+    uint256 self_a = 7;
+    // exact: -7/3, computed as written: -3, it must round up
+    int256 result = price_implementation(b, self_a);
+    assertGe(result, -2);
+}
+
+/// Replace with a call to the implementation of self.a /↓ b
+function price_implementation(int256 b, uint256 self_a) internal returns (int256) {
+    revert(\"not implemented\");
+}"
+        );
+
+        // The inputs that are not integers can not be passed to the implementation
+        let counterexample = Counterexample {
+            values: [("a".to_string(), BigRational::new(1.into(), 2.into()))].into(),
+            exact: BigRational::new(1.into(), 2.into()),
+            rounded: BigRational::zero(),
+            rounding: Rounding::Up,
+        };
+        assert!(foundry_test("half", "a", &counterexample).is_none());
+    }
+
+    #[test]
+    fn test_variables() {
+        let ids = ["a.b", "a_b", "result", "price_implementation", "a_b_1"];
+        let variables = variables(
+            ids.iter().map(ToString::to_string),
+            &["result", "price_implementation"],
+        );
+        let names: Vec<&str> = ids.iter().map(|id| variables[*id].as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "a_b",
+                "a_b_1",
+                "result_1",
+                "price_implementation_1",
+                "a_b_1_1"
+            ]
+        );
+    }
+}
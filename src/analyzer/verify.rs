@@ -0,0 +1,291 @@
+use std::fmt::{Display, Error, Formatter};
+
+use num_bigint::{BigInt, RandBigInt};
+use num_rational::BigRational;
+use num_traits::{One, Signed};
+use rand::Rng;
+
+use super::ast::{Expr, Rounding};
+use super::collect_ids;
+use super::evaluator::{self, Mode, Values};
+use super::interval::{Interval, Ranges};
+
+/// The inputs for which the formula, rounding as recommended, lands on the wrong side
+/// of its exact value.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Counterexample {
+    /// The values of the identifiers.
+    pub values: Values,
+    /// The exact value of the formula.
+    pub exact: BigRational,
+    /// The value with the operations rounding as recommended.
+    pub rounded: BigRational,
+    /// The direction the result must round to.
+    pub rounding: Rounding,
+}
+
+impl Counterexample {
+    /// How far the result is from the exact value on the wrong side.
+    pub fn error(&self) -> BigRational {
+        (&self.rounded - &self.exact).abs()
+    }
+}
+
+impl Display for Counterexample {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        let values: Vec<String> = self
+            .values
+            .iter()
+            .map(|(name, value)| format!("{name} = {value}"))
+            .collect();
+        let side = if self.rounded < self.exact {
+            "below"
+        } else {
+            "above"
+        };
+        write!(
+            fmt,
+            "{}: the result is {}, {side} the exact value {}, while it must round {}",
+            values.join(", "),
+            self.rounded,
+            self.exact,
+            self.rounding
+        )
+    }
+}
+
+/// The outcome of the randomized check of a formula.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Verification {
+    /// The number of inputs evaluated.
+    pub samples: usize,
+    /// The number of inputs the formula could not be evaluated for (divisions by zero,
+    /// irrational results).
+    pub skipped: usize,
+    /// Why the last skipped input could not be evaluated.
+    pub skip_reason: Option<String>,
+    /// The inputs found landing on the wrong side of the exact value: the first ones for
+    /// `verify`, the ones with the largest error for `search`.
+    pub counterexample: Option<Counterexample>,
+}
+
+impl Verification {
+    /// Whether no input could be evaluated: the absence of counterexample proves nothing.
+    pub fn is_inconclusive(&self) -> bool {
+        self.counterexample.is_none() && self.samples == 0
+    }
+
+    /// Whether most of the inputs could not be evaluated, the evaluated ones covering
+    /// little of the ranges.
+    pub fn is_mostly_skipped(&self) -> bool {
+        self.skipped > self.samples
+    }
+}
+
+impl Display for Verification {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        match &self.counterexample {
+            Some(counterexample) => write!(fmt, "counterexample {counterexample}")?,
+            None if self.is_inconclusive() => write!(fmt, "inconclusive, no sample evaluated")?,
+            None => write!(fmt, "no counterexample in {} samples", self.samples)?,
+        }
+        if let Some(reason) = &self.skip_reason {
+            write!(
+                fmt,
+                " ({} samples could not be evaluated: {reason})",
+                self.skipped
+            )?;
+        }
+        if self.is_mostly_skipped() && !self.is_inconclusive() {
+            write!(
+                fmt,
+                "\nwarning: most samples could not be evaluated, the check covers few inputs"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The smallest and largest integers of an interval. The unbounded ends are bounded by
+/// `int256` below and `uint256` above.
+pub(crate) fn integer_bounds(interval: &Interval) -> (BigInt, BigInt) {
+    let low = match &interval.low {
+        Some(bound) if !bound.closed && bound.value.is_integer() => bound.value.to_integer() + 1,
+        Some(bound) => bound.value.ceil().to_integer(),
+        None => -(BigInt::one() << 255u32),
+    };
+    let high = match &interval.high {
+        Some(bound) if !bound.closed && bound.value.is_integer() => bound.value.to_integer() - 1,
+        Some(bound) => bound.value.floor().to_integer(),
+        None => (BigInt::one() << 256u32) - 1,
+    };
+    (low, high)
+}
+
+/// Draws an integer of an interval. The distance to one of its ends is drawn with a
+/// uniformly random number of bits, so the small values and the values close to the
+/// ends are drawn as often as the large ones.
+///
+/// An interval without integers, e.g. `(0, 1)`, gives the middle of its ends.
+pub(crate) fn sample(interval: &Interval, rng: &mut impl Rng) -> BigRational {
+    let (low, high) = integer_bounds(interval);
+    if low > high {
+        return match (&interval.low, &interval.high) {
+            (Some(low), Some(high)) => (&low.value + &high.value) / BigInt::from(2),
+            _ => BigRational::from_integer(low),
+        };
+    }
+    let span: BigInt = &high - &low + 1;
+    let bits = rng.gen_range(0..=span.bits());
+    let limit = (BigInt::one() << bits).min(span);
+    let offset = rng.gen_bigint_range(&BigInt::from(0), &limit);
+    let value = if rng.gen_bool(0.5) {
+        low + offset
+    } else {
+        high - offset
+    };
+    BigRational::from_integer(value)
+}
+
+/// Evaluates an analyzed formula for random inputs, every operation rounding in the
+/// direction the analysis recommends, and compares the results with the exact values.
+///
+/// The identifiers are drawn as integers in their declared ranges, and in `uint256`
+/// without a range. A result rounding up must never be below the exact value, and a
+/// result rounding down never above it. The inputs the formula cannot be evaluated for
+/// are skipped, as are the ones for which a `**` is not an integer, the analysis not
+/// giving it a direction.
+pub fn verify(
+    ast: &Expr,
+    rounding: Rounding,
+    ranges: &Ranges,
+    samples: usize,
+    rng: &mut impl Rng,
+) -> Verification {
+    let mut ids = vec![];
+    collect_ids(ast, &mut ids);
+    ids.sort_unstable();
+    ids.dedup();
+
+    let uint256 = "[0, inf)".parse::<Interval>().unwrap();
+    let mut verification = Verification {
+        samples: 0,
+        skipped: 0,
+        skip_reason: None,
+        counterexample: None,
+    };
+
+    for _ in 0..samples {
+        let values: Values = ids
+            .iter()
+            .map(|id| {
+                let interval = ranges.get(id).unwrap_or(&uint256);
+                (id.clone(), sample(interval, rng))
+            })
+            .collect();
+
+        let evaluation = evaluator::eval(ast, &values, Mode::Exact).and_then(|exact| {
+            evaluator::eval(ast, &values, Mode::Annotated).map(|rounded| (exact, rounded))
+        });
+        let evaluation = evaluation
+            .map_err(|e| e.to_string())
+            .and_then(|evaluation| match evaluator::inexact_pow(ast, &values) {
+                Some(reason) => Err(reason),
+                None => Ok(evaluation),
+            });
+        let (exact, rounded) = match evaluation {
+            Ok(evaluation) => evaluation,
+            Err(reason) => {
+                verification.skipped += 1;
+                verification.skip_reason = Some(reason);
+                continue;
+            }
+        };
+        verification.samples += 1;
+
+        let wrong_side = match rounding {
+            Rounding::Up => rounded < exact,
+            Rounding::Down => rounded > exact,
+            Rounding::Init | Rounding::Unknown => false,
+        };
+        if wrong_side {
+            verification.counterexample = Some(Counterexample {
+                values,
+                exact,
+                rounded,
+                rounding,
+            });
+            break;
+        }
+    }
+
+    verification
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::fixtures::{self, ranges};
+    use crate::parser::arithmetic;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_sample() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let interval: Interval = "(0, 10]".parse().unwrap();
+        for _ in 0..100 {
+            let value = sample(&interval, &mut rng);
+            assert!(value.is_integer());
+            assert!(value > BigRational::from_integer(0.into()));
+            assert!(value <= BigRational::from_integer(10.into()));
+        }
+        let interval: Interval = "(0, 1)".parse().unwrap();
+        assert_eq!(
+            sample(&interval, &mut rng),
+            BigRational::new(1.into(), 2.into())
+        );
+    }
+
+    #[test]
+    fn test_verify() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let ranges = ranges(&[("b", "[1, 1000]")]);
+
+        let ast = arithmetic::ExprParser::new().parse("a *↑ 3 /↑ b").unwrap();
+        let verification = verify(&ast, Rounding::Up, &ranges, 100, &mut rng);
+        assert_eq!(verification.samples, 100);
+        assert!(verification.counterexample.is_none());
+
+        let ast = arithmetic::ExprParser::new().parse("a *↑ 3 /↓ b").unwrap();
+        let verification = verify(&ast, Rounding::Up, &ranges, 100, &mut rng);
+        let counterexample = verification.counterexample.unwrap();
+        assert!(counterexample.rounded < counterexample.exact);
+
+        let ast = arithmetic::ExprParser::new().parse("a / (b - b)").unwrap();
+        let verification = verify(&ast, Rounding::Up, &ranges, 10, &mut rng);
+        assert_eq!(verification.skipped, 10);
+        assert!(verification.skip_reason.is_some());
+        assert!(verification.is_inconclusive());
+        assert!(verification
+            .to_string()
+            .starts_with("inconclusive, no sample evaluated (10 samples could not be evaluated"));
+
+        // The truncation of b ** -1 is not a rounding of the analysis
+        let ast = arithmetic::ExprParser::new().parse("b *↑ 2 ** -1").unwrap();
+        let verification = verify(&ast, Rounding::Up, &ranges, 10, &mut rng);
+        assert!(verification.counterexample.is_none());
+        assert!(verification
+            .skip_reason
+            .unwrap()
+            .ends_with("the rounding of ** is not analyzed"));
+
+        // Most of the inputs divide by zero
+        let ranges = fixtures::ranges(&[("b", "[0, 1]"), ("c", "[0, 1]")]);
+        let ast = arithmetic::ExprParser::new().parse("a / (b * c)").unwrap();
+        let verification = verify(&ast, Rounding::Up, &ranges, 100, &mut rng);
+        assert!(!verification.is_inconclusive());
+        assert!(verification.is_mostly_skipped());
+        assert!(verification.to_string().contains("warning: most samples"));
+    }
+}
@@ -1,11 +1,29 @@
 use std::path::PathBuf;
 
 use clap::{Parser, Subcommand, ValueHint};
+use num_rational::BigRational;
 
-use roundme::analyzer::analyze;
+use roundme::analyzer::evaluator::{self, Mode};
+use roundme::analyzer::oracle::{InteractiveOracle, NoPromptOracle, Oracle};
+use roundme::analyzer::verify::Verification;
+use roundme::analyzer::{analyze_all, evaluate_all, search_all, verify_all, Verdict};
 use roundme::parser::{InputFormat, Parser as FormulaParser};
 use roundme::printer::{OutputFormat, Printer};
 
+/// Exit code used when `analyze --no-prompt` leaves assumptions unresolved, unless an
+/// operation rounds in the wrong direction. Distinct from the code 2 of the usage errors.
+pub const UNRESOLVED_EXIT_CODE: i32 = 5;
+
+/// Exit code used when an annotated operation or a library call rounds in the wrong direction.
+pub const WRONG_DIRECTION_EXIT_CODE: i32 = 3;
+
+/// Exit code used when `verify` or `search` finds inputs rounding on the wrong side of the
+/// exact value.
+pub const COUNTEREXAMPLE_EXIT_CODE: i32 = 4;
+
+/// Exit code used when `verify` or `search` could not evaluate any input of a formula.
+pub const INCONCLUSIVE_EXIT_CODE: i32 = 6;
+
 #[derive(Parser, Debug)]
 pub struct CliArgs {
     /// Formula config file to analyze
@@ -29,12 +47,61 @@ enum Commands {
         /// Output format, one of the [text, pdf]
         #[arg(short, long, value_enum, default_value = "text")]
         output_format: OutputFormat,
+
+        /// Never ask for assumptions, report the unanswered ones and exit with code 5
+        #[arg(long)]
+        no_prompt: bool,
+
+        /// Write the answered assumptions back into the formula config file
+        #[arg(long)]
+        save_assumptions: bool,
+    },
+
+    /// evaluate the formulas for the given values, exactly and with rounding
+    Evaluate {
+        /// Values of the identifiers, as name=value (e.g. amount=1e18)
+        #[arg(value_parser = parse_value)]
+        values: Vec<(String, BigRational)>,
+
+        /// How the operations round their results
+        #[arg(short, long, value_enum, default_value = "annotated")]
+        mode: Mode,
+    },
+
+    /// check the recommended rounding on random inputs drawn in the declared ranges
+    Verify {
+        /// Never ask for assumptions, the operations depending on them are truncated
+        #[arg(long)]
+        no_prompt: bool,
+
+        /// Number of inputs drawn per formula
+        #[arg(long, default_value_t = 1000)]
+        samples: usize,
+
+        /// Seed of the random inputs
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+    },
+
+    /// search inputs for which the formulas, as implemented, round on the wrong side
+    Search {
+        /// Largest number of inputs tried per formula
+        #[arg(long, default_value_t = 10_000)]
+        budget: usize,
+
+        /// Seed of the random inputs, when the combinations exceed the budget
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
     },
 
     /// delete the specified formula config file
     Clean,
 }
 
+fn parse_value(assignment: &str) -> anyhow::Result<(String, BigRational)> {
+    evaluator::parse_value(assignment)
+}
+
 pub trait Cmd {
     fn run(&self) -> anyhow::Result<()>;
 }
@@ -60,16 +127,99 @@ impl Cmd for CliArgs {
                 parser.init()?;
             }
 
-            Commands::Analyze { output_format } => {
-                let mut formula_config = parser.parse()?;
+            Commands::Analyze {
+                output_format,
+                no_prompt,
+                save_assumptions,
+            } => {
+                let mut formula_configs = parser.parse()?;
+
+                let oracle: &mut dyn Oracle = if no_prompt {
+                    &mut NoPromptOracle
+                } else {
+                    &mut InteractiveOracle
+                };
 
                 //println!("{:?}", &formula); // debug
-                // analyze the formula
-                let ast = analyze(&mut formula_config)?;
+                // analyze the formulas
+                let analyses = analyze_all(&mut formula_configs, oracle)?;
+
+                if save_assumptions {
+                    parser.update(&formula_configs)?;
+                }
 
                 // print the output
                 let printer = Printer::new(output_format);
-                printer.print(&ast, &formula_config)?
+                printer.print(&analyses, &formula_configs)?;
+
+                if analyses.iter().any(|analysis| {
+                    analysis
+                        .checks
+                        .iter()
+                        .any(|check| check.verdict() == Verdict::WrongDirection)
+                }) {
+                    std::process::exit(WRONG_DIRECTION_EXIT_CODE);
+                }
+
+                if analyses
+                    .iter()
+                    .any(|analysis| !analysis.unresolved.is_empty())
+                {
+                    std::process::exit(UNRESOLVED_EXIT_CODE);
+                }
+            }
+
+            Commands::Evaluate { ref values, mode } => {
+                let formula_configs = parser.parse()?;
+                let values = values.iter().cloned().collect();
+                let evaluations = evaluate_all(&formula_configs, &values, mode)?;
+                Printer::print_evaluations(&evaluations, &formula_configs, mode);
+            }
+
+            Commands::Verify {
+                no_prompt,
+                samples,
+                seed,
+            } => {
+                let mut formula_configs = parser.parse()?;
+
+                let oracle: &mut dyn Oracle = if no_prompt {
+                    &mut NoPromptOracle
+                } else {
+                    &mut InteractiveOracle
+                };
+                let analyses = analyze_all(&mut formula_configs, oracle)?;
+
+                let verifications = verify_all(&analyses, &formula_configs, samples, seed);
+                Printer::print_verifications(&verifications, &formula_configs);
+
+                if verifications
+                    .iter()
+                    .any(|verification| verification.counterexample.is_some())
+                {
+                    std::process::exit(COUNTEREXAMPLE_EXIT_CODE);
+                }
+
+                if verifications.iter().any(Verification::is_inconclusive) {
+                    std::process::exit(INCONCLUSIVE_EXIT_CODE);
+                }
+            }
+
+            Commands::Search { budget, seed } => {
+                let formula_configs = parser.parse()?;
+                let searches = search_all(&formula_configs, budget, seed)?;
+                Printer::print_searches(&searches, &formula_configs);
+
+                if searches
+                    .iter()
+                    .any(|search| search.counterexample.is_some())
+                {
+                    std::process::exit(COUNTEREXAMPLE_EXIT_CODE);
+                }
+
+                if searches.iter().any(Verification::is_inconclusive) {
+                    std::process::exit(INCONCLUSIVE_EXIT_CODE);
+                }
             }
 
             Commands::Clean => {
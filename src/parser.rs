@@ -6,14 +6,14 @@ mod yaml;
 
 use self::{
     input::ask_user_formula_config,
-    yaml::{clean, from_yaml_file, to_yaml_file},
+    yaml::{clean, from_yaml_file, to_yaml_file, update_yaml_file},
 };
 pub use formula_config::FormulaConfig;
 pub use input::ask_yes_no;
 pub use yaml::to_yaml_str;
 
 use lalrpop_util::lalrpop_mod;
-lalrpop_mod!(pub arithmetic, "/parser/arithmetic.rs"); // synthesized by LALRPOP
+lalrpop_mod!(#[allow(clippy::all)] pub arithmetic, "/parser/arithmetic.rs"); // synthesized by LALRPOP
 
 pub struct Parser {
     file_path: PathBuf,
@@ -52,15 +52,122 @@ impl Parser {
         }
     }
 
-    pub fn parse(&self) -> anyhow::Result<FormulaConfig> {
+    pub fn parse(&self) -> anyhow::Result<Vec<FormulaConfig>> {
         match self.input_format {
             InputFormat::YAML => from_yaml_file(self.file_path.as_path()),
         }
     }
 
+    /// Writes the assumptions learned during the analysis back into the config file.
+    pub fn update(&self, formula_configs: &[FormulaConfig]) -> anyhow::Result<()> {
+        match self.input_format {
+            InputFormat::YAML => update_yaml_file(self.file_path.as_path(), formula_configs),
+        }
+    }
+
     pub fn clean(&self) -> anyhow::Result<()> {
         match self.input_format {
             InputFormat::YAML => clean(self.file_path.as_path()),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::ast::Expr;
+
+    fn parse(formula: &str) -> String {
+        let ast = arithmetic::ExprParser::new().parse(formula).unwrap();
+        format!("{ast}")
+    }
+
+    #[test]
+    fn test_parse_precedence() {
+        assert_eq!(parse("a + b * c"), "(a + (b * c))");
+        assert_eq!(parse("a - b / c"), "(a - (b / c))");
+        assert_eq!(parse("a * b ** c"), "(a * (b ** c))");
+        assert_eq!(parse("a ** b * c"), "((a ** b) * c)");
+        assert_eq!(parse("a / b ** c / d"), "((a / (b ** c)) / d)");
+    }
+
+    #[test]
+    fn test_parse_unary_minus() {
+        assert_eq!(parse("-a * b"), "(-a * b)");
+        assert_eq!(parse("x * (-1)"), "(x * -1)");
+        assert_eq!(parse("a - -b"), "(a - -b)");
+        assert_eq!(parse("-a ** b"), "-(a ** b)");
+        assert_eq!(parse("a ** -b * c"), "((a ** -b) * c)");
+        assert_eq!(parse("-(a + b)"), "-(a + b)");
+        assert_eq!(parse("--a"), "--a");
+        assert_eq!(
+            *arithmetic::ExprParser::new().parse("-1").unwrap(),
+            Expr::Number((-1).into())
+        );
+    }
+
+    #[test]
+    fn test_parse_numbers() {
+        assert_eq!(parse("1e18 * a / 1_000"), "((1e18 * a) / 1_000)");
+        assert_eq!(parse("0.997 * 0xFF"), "(0.997 * 0xFF)");
+        assert_eq!(parse("a * -2.5e-3"), "(a * -2.5e-3)");
+        assert_eq!(parse("10 ** 18"), "(10 ** 18)");
+        assert_eq!(
+            parse("115792089237316195423570985008687907853269984665640564039457584007913129639935"),
+            "115792089237316195423570985008687907853269984665640564039457584007913129639935"
+        );
+    }
+
+    #[test]
+    fn test_parse_identifiers() {
+        assert_eq!(parse("balanceIn * a0"), "(balanceIn * a0)");
+        assert_eq!(parse("token0_reserve / _x"), "(token0_reserve / _x)");
+        assert_eq!(parse("self.totalSupply - 1"), "(self.totalSupply - 1)");
+        assert_eq!(parse("params.weight[i] ** 2"), "(params.weight[i] ** 2)");
+        assert_eq!(
+            parse("pools[0].balances[token.id]"),
+            "pools[0].balances[token.id]"
+        );
+        assert!(arithmetic::ExprParser::new().parse("a.0").is_err());
+    }
+
+    #[test]
+    fn test_parse_annotated() {
+        assert_eq!(parse("a *↓ b /↑ c"), "((a *↓ b) /↑ c)");
+        assert_eq!(parse("a*↑b ** c"), "(a *↑ (b ** c))");
+    }
+
+    #[test]
+    fn test_parse_conditional() {
+        assert_eq!(parse("x > y ? x - y : 0"), "(x > y ? (x - y) : 0)");
+        assert_eq!(
+            parse("a >= b ? c : d != e ? f : g + 1"),
+            "(a >= b ? c : (d != e ? f : (g + 1)))"
+        );
+        assert_eq!(parse("(a < b ? a : b) * 2"), "((a < b ? a : b) * 2)");
+        assert_eq!(parse("min(amount, balance)"), "min(amount, balance)");
+        assert!(arithmetic::ExprParser::new().parse("a > b").is_err());
+    }
+
+    #[test]
+    fn test_parse_calls() {
+        assert_eq!(
+            parse("FullMath.mulDiv(a, b * 2, c) + 1"),
+            "(FullMath.mulDiv(a, b * 2, c) + 1)"
+        );
+        assert_eq!(
+            parse("mulWadUp(x, divWadDown(y, z))"),
+            "mulWadUp(x, divWadDown(y, z))"
+        );
+        assert!(arithmetic::ExprParser::new().parse("foo(a)").is_err());
+        assert!(arithmetic::ExprParser::new().parse("mulDiv(a, b)").is_err());
+    }
+
+    #[test]
+    fn test_parse_associativity() {
+        assert_eq!(parse("a - b - c"), "((a - b) - c)");
+        assert_eq!(parse("a / b / c"), "((a / b) / c)");
+        assert_eq!(parse("a ** b ** c"), "(a ** (b ** c))");
+        assert_eq!(parse("(a ** b) ** c"), "((a ** b) ** c)");
+    }
+}
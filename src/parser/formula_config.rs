@@ -1,9 +1,16 @@
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::analyzer::interval::Ranges;
+use crate::analyzer::overflow::Types;
+use crate::analyzer::scale::Decimals;
+
 /// Configuration struct for rounding numbers.
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct FormulaConfig {
+    /// Optional name of the formula, used in the reports.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
     /// The formula used for rounding.
     pub formula: String,
     /// Whether to round up or down.
@@ -14,20 +21,44 @@ pub struct FormulaConfig {
     /// Optional list of values less than one to improve the rounding analysis.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub greater_than_one: Option<Vec<String>>,
+    /// Optional ranges of the identifiers, e.g. `a: "[0, inf)"`, answering the `**`
+    /// questions when they can.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ranges: Option<Ranges>,
+    /// Optional types of the identifiers, e.g. `a: uint128` or `w: "1e18"`, enabling the
+    /// overflow analysis.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub types: Option<Types>,
+    /// Optional decimals of the identifiers, e.g. `a: 18` for a WAD, enabling the
+    /// scale checks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decimals: Option<Decimals>,
 }
 
 impl Default for FormulaConfig {
     fn default() -> Self {
         Self {
+            name: None,
             formula: "((a * b)**(e/f)) / (c * d)".to_string(),
             round_up: true,
             less_than_one: None, // Default value is None, so it's optional and won't appear in the default YAML.
             greater_than_one: None, // Default value is None, so it's optional and won't appear in the default YAML.
+            ranges: None,
+            types: None,
+            decimals: None,
         }
     }
 }
 
 impl FormulaConfig {
+    /// Returns the name of the formula, or its position in the config file if it has none.
+    pub fn label(&self, index: usize) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => format!("formula {}", index + 1),
+        }
+    }
+
     // Add a value to the `less_than_one` list
     pub fn add_less_than_one(&mut self, value: String) {
         match self.less_than_one {
@@ -1,6 +1,7 @@
 use std::io::{self, Write};
 
 use crate::analyzer::ast::{Expr, Opcode};
+use crate::analyzer::builtins::{self, Semantics};
 
 use super::arithmetic;
 use super::FormulaConfig;
@@ -13,8 +14,7 @@ pub fn ask_user_formula_config() -> anyhow::Result<FormulaConfig> {
     let mut formula_config = FormulaConfig {
         formula: formula_user,
         round_up: rounding,
-        less_than_one: None,
-        greater_than_one: None,
+        ..Default::default()
     };
 
     find_less_greater_than_one(&expr, &mut formula_config);
@@ -34,8 +34,8 @@ fn ask_formula() -> anyhow::Result<(String, Box<Expr>)> {
     // Get user input
     io::stdin().read_line(&mut input)?;
 
-    // Trim the input and convert it to lowercase
-    let formula = input.trim().to_lowercase();
+    // Trim the input, the names are kept as written
+    let formula = input.trim().to_string();
     let parse_expression = arithmetic::ExprParser::new();
 
     match parse_expression.parse(&formula) {
@@ -44,7 +44,6 @@ fn ask_formula() -> anyhow::Result<(String, Box<Expr>)> {
             println!("Can't parse the expression: {err}");
             println!("Make sure to:");
             println!("- Have the correct number of parenthesis");
-            println!("- Do not use number in the ID name (ex: do not name a variable a0)");
             println!("- Use ** for power (and not ^)");
             ask_formula()
         }
@@ -90,6 +89,27 @@ fn find_less_greater_than_one(expr: &Expr, formula_config: &mut FormulaConfig) {
 fn visit(expr: &Expr, formula_config: &mut FormulaConfig) {
     match expr {
         Expr::Number(_) | Expr::Id(_) | Expr::Error => (),
+        Expr::Neg(expr) => visit(expr, formula_config),
+        Expr::Cond(condition, then, otherwise) => {
+            visit(&condition.left, formula_config);
+            visit(&condition.right, formula_config);
+            visit(then, formula_config);
+            visit(otherwise, formula_config);
+        }
+        Expr::Call(name, args, _) => {
+            if builtins::lookup(name).is_some_and(|builtin| {
+                matches!(
+                    builtin.semantics,
+                    Semantics::Pow | Semantics::PowWad | Semantics::RPow
+                )
+            }) {
+                // We ignore if the following fail
+                let _ = handle_pow(&args[0], formula_config);
+            }
+            for arg in args {
+                visit(arg, formula_config);
+            }
+        }
         Expr::Op(left, op, right) => {
             if let Opcode::Pow = op {
                 // We ignore if the following fail
@@ -107,7 +127,7 @@ fn handle_pow(left: &Expr, formula_config: &mut FormulaConfig) -> anyhow::Result
     if formula_config
         .less_than_one
         .as_ref()
-        .map_or(false, |vec| vec.contains(&expr_str))
+        .is_some_and(|vec| vec.contains(&expr_str))
     {
         return Ok(());
     }
@@ -115,7 +135,7 @@ fn handle_pow(left: &Expr, formula_config: &mut FormulaConfig) -> anyhow::Result
     if formula_config
         .greater_than_one
         .as_ref()
-        .map_or(false, |vec| vec.contains(&expr_str))
+        .is_some_and(|vec| vec.contains(&expr_str))
     {
         return Ok(());
     }
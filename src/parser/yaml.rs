@@ -3,6 +3,8 @@ use std::fs::File;
 use std::io::{Read, Write};
 use std::path::Path;
 
+use serde::Deserialize;
+
 use super::formula_config::FormulaConfig;
 
 /// Converts a `FormulaConfig
@@ -21,8 +23,8 @@ pub fn to_yaml_str(formula_config: &FormulaConfig) -> Result<String> {
         .map_err(|e| anyhow!("Failed to serialize default config: {}", e))
 }
 
-/// Parse a FormulaConfig object from the provided YAML file
-pub fn from_yaml_file(file_path: &Path) -> Result<FormulaConfig> {
+/// Parse the FormulaConfig objects from the provided YAML file, one per YAML document
+pub fn from_yaml_file(file_path: &Path) -> Result<Vec<FormulaConfig>> {
     let file_path_str = file_path.to_str().unwrap();
 
     let mut file = File::open(file_path)
@@ -32,10 +34,30 @@ pub fn from_yaml_file(file_path: &Path) -> Result<FormulaConfig> {
     file.read_to_string(&mut contents)
         .map_err(|e| anyhow!("Failed to read file {}: {}", file_path_str, e))?;
 
-    let formula_config: FormulaConfig =
-        serde_yaml::from_str(&contents).map_err(|e| anyhow!("Failed to parse YAML: {}", e))?;
+    let mut formula_configs = vec![];
+    for document in serde_yaml::Deserializer::from_str(&contents) {
+        let value = serde_yaml::Value::deserialize(document)
+            .map_err(|e| anyhow!("Failed to parse YAML: {}", e))?;
+        // Skip the documents only made of comments
+        if is_empty_document(&value) {
+            continue;
+        }
+        let formula_config: FormulaConfig =
+            serde_yaml::from_value(value).map_err(|e| anyhow!("Failed to parse YAML: {}", e))?;
+        formula_configs.push(formula_config);
+    }
+
+    if formula_configs.is_empty() {
+        return Err(anyhow!("No formula found in {}", file_path_str));
+    }
+
+    Ok(formula_configs)
+}
 
-    Ok(formula_config)
+/// Returns whether a YAML document holds no formula: nothing but markers and comments,
+/// or an explicit null (`~`, `null`).
+fn is_empty_document(value: &serde_yaml::Value) -> bool {
+    value.is_null()
 }
 
 /// Creates a YAML file from provided FormulaConfig at provided file path
@@ -45,7 +67,7 @@ pub fn to_yaml_file(file_path: &Path, formula_config: &FormulaConfig) -> Result<
     if file_path.exists() {
         return Err(anyhow!("Config file '{}' already exists.", file_path_str));
     }
-    let yaml = to_yaml_str(&formula_config)?;
+    let yaml = to_yaml_str(formula_config)?;
 
     let mut file = File::create(file_path)
         .map_err(|e| anyhow!("Failed to create file {}: {}", file_path_str, e))?;
@@ -57,6 +79,135 @@ pub fn to_yaml_file(file_path: &Path, formula_config: &FormulaConfig) -> Result<
     Ok(())
 }
 
+/// Writes the `less_than_one` and `greater_than_one` lists of the provided FormulaConfigs
+/// back into the YAML file at the provided file path, one FormulaConfig per YAML document.
+///
+/// The file is patched in place rather than re-serialized, so the comments and the
+/// order of the keys are preserved. Only the lists that changed are rewritten.
+pub fn update_yaml_file(file_path: &Path, formula_configs: &[FormulaConfig]) -> Result<()> {
+    let file_path_str = file_path.to_str().unwrap();
+
+    let contents = std::fs::read_to_string(file_path)
+        .map_err(|e| anyhow!("Failed to read file {}: {}", file_path_str, e))?;
+
+    let mut formula_configs = formula_configs.iter();
+    let mut updated = String::new();
+    for document in split_documents(&contents) {
+        if !has_content(&document) {
+            updated.push_str(&document);
+            continue;
+        }
+        let formula_config = formula_configs
+            .next()
+            .ok_or_else(|| anyhow!("{} has more formulas than analyzed", file_path_str))?;
+        updated.push_str(&update_yaml_str(&document, formula_config)?);
+    }
+
+    if updated == contents {
+        return Ok(());
+    }
+
+    std::fs::write(file_path, updated)
+        .map_err(|e| anyhow!("Failed to write to file {}: {}", file_path_str, e))?;
+
+    println!("Updated the assumptions of the formula config file {file_path_str}");
+
+    Ok(())
+}
+
+/// Splits a YAML stream into its documents, each one starting with its `---` marker if any.
+/// Concatenating the documents gives back the stream.
+fn split_documents(contents: &str) -> Vec<String> {
+    let mut documents = vec![String::new()];
+    for line in contents.split_inclusive('\n') {
+        if is_marker(line) && !documents.last().unwrap().is_empty() {
+            documents.push(String::new());
+        }
+        documents.last_mut().unwrap().push_str(line);
+    }
+    documents
+}
+
+/// Returns whether a line starts a YAML document: `---` followed by a space or the end
+/// of the line.
+fn is_marker(line: &str) -> bool {
+    line.strip_prefix("---")
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+}
+
+/// Returns whether a YAML document holds a formula, as `from_yaml_file` reads it. A
+/// document failing to parse counts as one, so that the error is reported.
+fn has_content(document: &str) -> bool {
+    serde_yaml::Deserializer::from_str(document).any(|document| {
+        serde_yaml::Value::deserialize(document).map_or(true, |value| !is_empty_document(&value))
+    })
+}
+
+/// Patches the assumption lists of a YAML document, see `update_yaml_file`.
+fn update_yaml_str(document: &str, formula_config: &FormulaConfig) -> Result<String> {
+    let current: FormulaConfig =
+        serde_yaml::from_str(document).map_err(|e| anyhow!("Failed to parse YAML: {}", e))?;
+
+    let mut document = document.to_string();
+    if current.less_than_one != formula_config.less_than_one {
+        if let Some(values) = &formula_config.less_than_one {
+            document = set_flow_list(&document, "less_than_one", values);
+        }
+    }
+    if current.greater_than_one != formula_config.greater_than_one {
+        if let Some(values) = &formula_config.greater_than_one {
+            document = set_flow_list(&document, "greater_than_one", values);
+        }
+    }
+    Ok(document)
+}
+
+/// Sets the top-level `key` of a YAML document to a flow sequence of `values`.
+///
+/// An existing entry is replaced in place (including a block sequence spanning several
+/// lines), keeping a trailing comment on its first line. A missing entry is appended at
+/// the end of the document.
+fn set_flow_list(document: &str, key: &str, values: &[String]) -> String {
+    let items: Vec<String> = values
+        .iter()
+        .map(|value| format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\"")))
+        .collect();
+    let entry = format!("{key}: [{}]", items.join(", "));
+
+    let mut lines: Vec<String> = document.lines().map(str::to_string).collect();
+
+    let is_key_line = |line: &String| {
+        line.strip_prefix(key)
+            .is_some_and(|rest| rest.trim_start().starts_with(':'))
+    };
+
+    if let Some(start) = lines.iter().position(is_key_line) {
+        // Keep a comment following a flow sequence, e.g. `key: ["a"] # comment`
+        let comment = lines[start]
+            .rfind(']')
+            .map(|end| lines[start][end + 1..].trim())
+            .filter(|rest| rest.starts_with('#'))
+            .map(|comment| format!(" {comment}"))
+            .unwrap_or_default();
+
+        let mut end = start + 1;
+        while end < lines.len()
+            && (lines[end].starts_with(' ')
+                || lines[end].starts_with('\t')
+                || lines[end].starts_with('-'))
+        {
+            end += 1;
+        }
+        lines.splice(start..end, [format!("{entry}{comment}")]);
+    } else {
+        lines.push(entry);
+    }
+
+    let mut updated = lines.join("\n");
+    updated.push('\n');
+    updated
+}
+
 /// Delete the YAML formula config file
 pub fn clean(file_path: &Path) -> Result<()> {
     if file_path.exists() {
@@ -68,3 +219,115 @@ pub fn clean(file_path: &Path) -> Result<()> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::fixtures;
+
+    fn config(
+        less_than_one: Option<Vec<&str>>,
+        greater_than_one: Option<Vec<&str>>,
+    ) -> FormulaConfig {
+        let to_vec = |values: Vec<&str>| values.into_iter().map(str::to_string).collect();
+        FormulaConfig {
+            less_than_one: less_than_one.map(to_vec),
+            greater_than_one: greater_than_one.map(to_vec),
+            ..fixtures::config("a ** b", true)
+        }
+    }
+
+    #[test]
+    fn test_update_appends_missing_list() {
+        let document = "---\n# comment\nformula: a ** b\nround_up: true\n";
+        let updated = update_yaml_str(document, &config(Some(vec!["a"]), None)).unwrap();
+        assert_eq!(
+            updated,
+            "---\n# comment\nformula: a ** b\nround_up: true\nless_than_one: [\"a\"]\n"
+        );
+    }
+
+    #[test]
+    fn test_update_replaces_existing_list() {
+        let document = "formula: a ** b\ngreater_than_one: [\"c\"] # optional\nround_up: true\n";
+        let updated = update_yaml_str(document, &config(None, Some(vec!["c", "a"]))).unwrap();
+        assert_eq!(
+            updated,
+            "formula: a ** b\ngreater_than_one: [\"c\", \"a\"] # optional\nround_up: true\n"
+        );
+    }
+
+    #[test]
+    fn test_update_replaces_block_list() {
+        let document = "formula: a ** b\nless_than_one:\n  - c\n  - d\nround_up: true\n";
+        let updated = update_yaml_str(document, &config(Some(vec!["c", "d", "a"]), None)).unwrap();
+        assert_eq!(
+            updated,
+            "formula: a ** b\nless_than_one: [\"c\", \"d\", \"a\"]\nround_up: true\n"
+        );
+    }
+
+    #[test]
+    fn test_update_keeps_unchanged_document() {
+        let document = "formula: a ** b\nless_than_one:\n  - a\nround_up: true";
+        let updated = update_yaml_str(document, &config(Some(vec!["a"]), None)).unwrap();
+        assert_eq!(updated, document);
+    }
+
+    #[test]
+    fn test_update_yaml_file() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), "formula: a ** b\nround_up: true\n").unwrap();
+
+        update_yaml_file(file.path(), &[config(None, Some(vec!["(a * b)"]))]).unwrap();
+
+        let parsed = from_yaml_file(file.path()).unwrap();
+        assert_eq!(parsed, vec![config(None, Some(vec!["(a * b)"]))]);
+    }
+
+    #[test]
+    fn test_from_yaml_file_multi_documents() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let contents = "---\n# first\nname: first\nformula: a ** b\nround_up: true\n---\nformula: a ** b\nround_up: true\n---\n# nothing\n";
+        std::fs::write(file.path(), contents).unwrap();
+
+        let mut first = config(None, None);
+        first.name = Some("first".to_string());
+        let parsed = from_yaml_file(file.path()).unwrap();
+        assert_eq!(parsed, vec![first, config(None, None)]);
+    }
+
+    #[test]
+    fn test_update_yaml_file_multi_documents() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let contents = "---\n# first\nformula: a ** b\nround_up: true\n---\n# second\nformula: a ** b\nround_up: true\n";
+        std::fs::write(file.path(), contents).unwrap();
+
+        let configs = [config(None, None), config(Some(vec!["a"]), None)];
+        update_yaml_file(file.path(), &configs).unwrap();
+
+        let updated = std::fs::read_to_string(file.path()).unwrap();
+        assert_eq!(
+            updated,
+            "---\n# first\nformula: a ** b\nround_up: true\n---\n# second\nformula: a ** b\nround_up: true\nless_than_one: [\"a\"]\n"
+        );
+
+        // The null documents are skipped as when reading the file
+        let contents = "--- ~\n---\nnull\n---\nformula: a ** b\nround_up: true\n";
+        std::fs::write(file.path(), contents).unwrap();
+        assert_eq!(from_yaml_file(file.path()).unwrap().len(), 1);
+        update_yaml_file(file.path(), &[config(Some(vec!["a"]), None)]).unwrap();
+        let updated = std::fs::read_to_string(file.path()).unwrap();
+        assert_eq!(updated, format!("{contents}less_than_one: [\"a\"]\n"));
+    }
+
+    #[test]
+    fn test_split_documents() {
+        // ---- is not a marker
+        let contents = "a: 1\n----\n---\nb: 2\n--- # c\n";
+        assert_eq!(
+            split_documents(contents),
+            vec!["a: 1\n----\n", "---\nb: 2\n", "--- # c\n"]
+        );
+    }
+}
@@ -1,6 +1,9 @@
 use clap::ValueEnum;
 
-use crate::{analyzer::ast::Expr, FormulaConfig};
+use crate::{
+    analyzer::{evaluator::Mode, search, verify::Verification, Analysis, Evaluation},
+    FormulaConfig,
+};
 
 mod latex_generator;
 
@@ -26,30 +29,196 @@ impl Printer {
 }
 
 impl Printer {
-    pub fn print(&self, ast: &Expr, formula_config: &FormulaConfig) -> anyhow::Result<()> {
+    /// Prints a combined report of the analyses, `analyses[i]` being the analysis of
+    /// `formula_configs[i]`.
+    pub fn print(
+        &self,
+        analyses: &[Analysis],
+        formula_configs: &[FormulaConfig],
+    ) -> anyhow::Result<()> {
         match self.output_format {
-            OutputFormat::Text => Printer::print_text(ast)?,
-            OutputFormat::PDF => Printer::print_pdf(ast, formula_config)?,
+            OutputFormat::Text => Printer::print_text(analyses, formula_configs)?,
+            OutputFormat::PDF => Printer::print_pdf(analyses, formula_configs)?,
         }
 
         println!("{DISCLAIMER}");
         Ok(())
     }
+
+    /// Prints the values of the formulas, `evaluations[i]` being the value of
+    /// `formula_configs[i]`.
+    pub fn print_evaluations(
+        evaluations: &[Evaluation],
+        formula_configs: &[FormulaConfig],
+        mode: Mode,
+    ) {
+        let mode = mode
+            .to_possible_value()
+            .map_or_else(String::new, |value| value.get_name().to_string());
+        for (index, (evaluation, formula_config)) in
+            evaluations.iter().zip(formula_configs).enumerate()
+        {
+            if index > 0 {
+                println!();
+            }
+            println!(
+                "[{}] {}",
+                formula_config.label(index),
+                formula_config.formula
+            );
+            println!("exact: {}", evaluation.exact);
+            println!("{mode}: {}", evaluation.rounded);
+            println!("error: {}", evaluation.error());
+        }
+    }
+
+    /// Prints the outcome of the randomized checks, `verifications[i]` being the check
+    /// of `formula_configs[i]`.
+    pub fn print_verifications(verifications: &[Verification], formula_configs: &[FormulaConfig]) {
+        for (index, (verification, formula_config)) in
+            verifications.iter().zip(formula_configs).enumerate()
+        {
+            println!("[{}] {verification}", formula_config.label(index));
+        }
+        println!("{DISCLAIMER}");
+    }
+
+    /// Prints the outcome of the counterexample searches, `searches[i]` being the
+    /// search of `formula_configs[i]`, with a Foundry test reproducing each
+    /// counterexample.
+    pub fn print_searches(searches: &[Verification], formula_configs: &[FormulaConfig]) {
+        for (index, (search, formula_config)) in searches.iter().zip(formula_configs).enumerate() {
+            let label = formula_config.label(index);
+            println!("[{label}] {search}");
+            if let Some(counterexample) = &search.counterexample {
+                println!();
+                match search::foundry_test(&label, &formula_config.formula, counterexample) {
+                    Some(test) => println!("{test}"),
+                    None => println!("No Foundry test, the inputs are not integers"),
+                }
+                println!();
+            }
+        }
+        println!("{DISCLAIMER}");
+    }
 }
 
 impl Printer {
-    /// Generates a PDF from the given expression AST using the provided configuration.
+    /// Generates a PDF from the given analyses using the provided configurations.
     /// Returns a `Result` indicating whether the operation was successful or not.
-    fn print_pdf(ast: &Expr, formula_config: &FormulaConfig) -> anyhow::Result<()> {
-        let latex_result = latex_generator::generate(ast, formula_config)?;
+    fn print_pdf(analyses: &[Analysis], formula_configs: &[FormulaConfig]) -> anyhow::Result<()> {
+        let latex_result = latex_generator::generate(analyses, formula_configs)?;
         latex_generator::write(&latex_result)?;
         Ok(())
     }
 
-    fn print_text(ast: &Expr) -> anyhow::Result<()> {
-        println!("");
+    fn print_text(analyses: &[Analysis], formula_configs: &[FormulaConfig]) -> anyhow::Result<()> {
+        println!();
         println!("Report:");
-        println!("{ast}");
+
+        for (index, (analysis, formula_config)) in analyses.iter().zip(formula_configs).enumerate()
+        {
+            // A single unnamed formula does not need a title
+            if formula_configs.len() > 1 || formula_config.name.is_some() {
+                if index > 0 {
+                    println!();
+                }
+                println!("[{}]", formula_config.label(index));
+            }
+
+            println!("{}", analysis.ast);
+            if let Some(decimals) = analysis.decimals {
+                println!("Decimals of the result: {decimals}");
+            }
+
+            if !analysis.values.is_empty() {
+                let width = analysis
+                    .values
+                    .iter()
+                    .map(|(value, _)| value.chars().count())
+                    .max()
+                    .unwrap_or(0)
+                    .max("Value".len());
+                println!();
+                println!("{:<width$}  Rounding", "Value");
+                for (value, rounding) in &analysis.values {
+                    println!("{value:<width$}  {rounding}");
+                }
+            }
+
+            if !analysis.unresolved.is_empty() {
+                println!();
+                println!("Unresolved assumptions (↕ marks the affected operations):");
+                for expr in &analysis.unresolved {
+                    println!("- Is {expr} greater than 1?");
+                }
+            }
+
+            if !analysis.undetermined.is_empty() {
+                println!();
+                println!("Undetermined monotonicity (↕ marks the affected operations):");
+                for explanation in &analysis.undetermined {
+                    println!("- {explanation}");
+                }
+            }
+
+            if !analysis.underflows.is_empty() {
+                println!();
+                println!("Possible underflows:");
+                for underflow in &analysis.underflows {
+                    println!("- {underflow}");
+                }
+            }
+
+            if !analysis.precision_losses.is_empty() {
+                println!();
+                println!("Divisions before multiplications:");
+                for loss in &analysis.precision_losses {
+                    println!("- {loss}");
+                }
+            }
+
+            if !analysis.magnitudes.is_empty() {
+                println!();
+                println!("Magnitudes:");
+                for magnitude in &analysis.magnitudes {
+                    println!("- {magnitude}");
+                }
+            }
+
+            if let Some(total_error) = &analysis.total_error {
+                println!();
+                println!("Rounding error bounds:");
+                for bound in &analysis.error_bounds {
+                    println!("- {bound}");
+                }
+                println!("Total: {total_error}");
+            }
+
+            if !analysis.scale_mismatches.is_empty() {
+                println!();
+                println!("Decimals mismatches:");
+                for mismatch in &analysis.scale_mismatches {
+                    println!("- {mismatch}");
+                }
+            }
+
+            if !analysis.conflicts.is_empty() {
+                println!();
+                println!("Conflicting rounding requirements:");
+                for conflict in &analysis.conflicts {
+                    println!("- {conflict}");
+                }
+            }
+
+            if !analysis.checks.is_empty() {
+                println!();
+                println!("Implemented rounding:");
+                for check in &analysis.checks {
+                    println!("- {check}");
+                }
+            }
+        }
         Ok(())
     }
 }
@@ -5,10 +5,14 @@ use std::process::{Command, Stdio};
 use std::str::FromStr;
 
 use anyhow::{anyhow, Result};
-use latex::{Document, DocumentClass, Section};
+use latex::{Document, DocumentClass, List, ListKind, Section};
 
 use super::DISCLAIMER;
-use crate::analyzer::ast::{Expr, Opcode, Rounding};
+use crate::analyzer::ast::{Comparison, Expr, Opcode, Rounding};
+use crate::analyzer::builtins::{self, Semantics};
+use crate::analyzer::error_bound::{approximate, ErrorBound};
+use crate::analyzer::overflow::Magnitude;
+use crate::analyzer::{Analysis, ValueRounding};
 use crate::parser::to_yaml_str;
 use crate::FormulaConfig;
 
@@ -23,8 +27,59 @@ use crate::FormulaConfig;
 /// A string representation of the expression in LaTeX format.
 fn visit(expr: &Expr) -> String {
     match expr {
-        Expr::Number(n) => n.to_string(),
-        Expr::Id(n) => n.to_string(),
+        // The digit separators are not valid in math mode
+        Expr::Number(n) => n.text.replace('_', ""),
+        Expr::Id(n) => escape(n),
+        Expr::Neg(e) => format!("-{{{}}}", visit(e)),
+        Expr::Cond(condition, then, otherwise) => {
+            let comparison = match condition.comparison {
+                Comparison::Lt => "<",
+                Comparison::Le => "\\leq",
+                Comparison::Gt => ">",
+                Comparison::Ge => "\\geq",
+                Comparison::Eq => "=",
+                Comparison::Ne => "\\neq",
+            };
+            format!(
+                "\\left\\{{\\begin{{array}}{{ll}} {{{}}} & \\text{{if }} {{{}}} {comparison} {{{}}} \\\\ {{{}}} & \\text{{otherwise}} \\end{{array}}\\right.",
+                visit(then),
+                visit(&condition.left),
+                visit(&condition.right),
+                visit(otherwise)
+            )
+        }
+        Expr::Call(name, args, call_rounding) => {
+            let direction: &str = match *call_rounding.borrow() {
+                Rounding::Init => "",
+                Rounding::Up => r"\uparrow",
+                Rounding::Down => r"\downarrow",
+                Rounding::Unknown => r"\updownarrow",
+            };
+            let args: Vec<String> = args
+                .iter()
+                .map(|arg| format!("{{{}}}", visit(arg)))
+                .collect();
+            let semantics = builtins::lookup(name).map(|builtin| builtin.semantics);
+            let function = match semantics {
+                Some(Semantics::Sqrt) => format!("\\sqrt{{{}}}", args[0]),
+                Some(Semantics::Ln) => format!("\\ln({})", args[0]),
+                Some(Semantics::Exp) => format!("e^{{{}}}", args[0]),
+                Some(Semantics::Log2) => format!("\\log_2({})", args[0]),
+                Some(Semantics::Abs) => format!("\\left|{}\\right|", args[0]),
+                _ => {
+                    return format!(
+                        "\\mathrm{{{}}}_{{{direction}}}({})",
+                        escape(name),
+                        args.join(", ")
+                    )
+                }
+            };
+            if direction.is_empty() {
+                function
+            } else {
+                format!("{{{function}}}_{{{direction}}}")
+            }
+        }
         Expr::Op(left, op, right) => {
             let left_str = visit(left);
             let right_str = visit(right);
@@ -35,7 +90,7 @@ fn visit(expr: &Expr) -> String {
                         Rounding::Init => "",
                         Rounding::Up => r"\uparrow",
                         Rounding::Down => r"\downarrow",
-                        Rounding::Unknown => "updownarrow",
+                        Rounding::Unknown => r"\updownarrow",
                     };
                     return format!("(\\frac{{{left_str}}}{{{right_str}}}{{{direction}}})");
                 }
@@ -46,7 +101,7 @@ fn visit(expr: &Expr) -> String {
                         Rounding::Init => "",
                         Rounding::Up => r"\uparrow",
                         Rounding::Down => r"\downarrow",
-                        Rounding::Unknown => "updownarrow",
+                        Rounding::Unknown => r"\updownarrow",
                     };
                     format!("}} *_{direction} {{")
                 }
@@ -58,37 +113,203 @@ fn visit(expr: &Expr) -> String {
     }
 }
 
+/// Escapes the LaTeX special characters of a plain text.
+fn escape(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '_' | '%' | '&' | '#' | '$' | '{' | '}' => format!("\\{c}"),
+            // The OT1 encoding prints them as ¡, ¿ and —
+            '<' => String::from(r"\textless{}"),
+            '>' => String::from(r"\textgreater{}"),
+            '|' => String::from(r"\textbar{}"),
+            '↑' => String::from(r"$\uparrow$"),
+            '↓' => String::from(r"$\downarrow$"),
+            '↕' => String::from(r"$\updownarrow$"),
+            _ => c.to_string(),
+        })
+        .collect()
+}
+
+/// Renders the rounding required for each value as a LaTeX table.
+fn values_table(values: &[(String, ValueRounding)]) -> String {
+    let rows: String = values
+        .iter()
+        .map(|(value, rounding)| format!("{} & {} \\\\\n", escape(value), rounding))
+        .collect();
+    format!(
+        "\\begin{{tabular}}{{ll}}\n\\hline\nValue & Rounding \\\\\n\\hline\n{rows}\\hline\n\\end{{tabular}}"
+    )
+}
+
+/// Renders the worst-case magnitude of each operation as a LaTeX table.
+fn magnitudes_table(magnitudes: &[Magnitude]) -> String {
+    let rows: String = magnitudes
+        .iter()
+        .map(|magnitude| {
+            let bound = match magnitude.bits() {
+                Some(bits) => format!("$< 2^{{{bits}}}$"),
+                None => String::from("unbounded"),
+            };
+            let overflow = if magnitude.overflows() { "yes" } else { "" };
+            format!(
+                "{} & {bound} & {overflow} \\\\\n",
+                escape(&magnitude.expression)
+            )
+        })
+        .collect();
+    format!(
+        "\\begin{{tabular}}{{lll}}\n\\hline\nOperation & Magnitude & Overflow \\\\\n\\hline\n{rows}\\hline\n\\end{{tabular}}"
+    )
+}
+
+/// Renders the rounding error bound of each operation, and of the result, as a LaTeX
+/// table.
+fn error_bounds_table(bounds: &[ErrorBound], total: &ErrorBound) -> String {
+    let row = |label: &str, bound: &ErrorBound| {
+        let absolute = bound.absolute.as_ref().map_or_else(
+            || String::from("unbounded"),
+            |error| format!("$\\pm${}", approximate(error)),
+        );
+        let relative = bound
+            .relative
+            .as_ref()
+            .map_or_else(String::new, |error| format!("$\\pm${}", approximate(error)));
+        format!("{} & {absolute} & {relative} \\\\\n", escape(label))
+    };
+    let rows: String = bounds
+        .iter()
+        .map(|bound| row(&bound.expression, bound))
+        .collect();
+    format!(
+        "\\begin{{tabular}}{{lll}}\n\\hline\nOperation & Absolute error & Relative error \\\\\n\\hline\n{rows}\\hline\n{}\\hline\n\\end{{tabular}}",
+        row("Total", total)
+    )
+}
+
 /// Generates a LaTeX document containing the configuration and analysis of a given expression.
 ///
 /// # Arguments
 ///
-/// * `expr` - An `Expr` struct representing the expression to be analyzed.
-/// * `config` - A `FormulaConfig` struct containing the configuration for the analysis.
+/// * `analyses` - The analyses of the formulas.
+/// * `configs` - The `FormulaConfig` of each analysis.
 ///
 /// # Returns
 ///
 /// A `Result` containing a `String` with the LaTeX document, or an `anyhow::Error` if an error occurred.
-pub fn generate(expr: &Expr, config: &FormulaConfig) -> Result<String> {
+pub fn generate(analyses: &[Analysis], configs: &[FormulaConfig]) -> Result<String> {
     let mut doc = Document::new(DocumentClass::Article);
 
     doc.preamble.use_package("hyperref");
+    doc.preamble.use_package("amsmath");
 
     // Set some metadata for the document
     doc.preamble.title("Round me analysis");
     doc.preamble.author("roundme");
 
     let mut section_1 = Section::new("Config");
-    let output = format!(
-        "\\begin{{verbatim}} {} \\end{{verbatim}}",
-        to_yaml_str(config)?
-    );
+    let yaml = configs
+        .iter()
+        .map(to_yaml_str)
+        .collect::<Result<Vec<_>>>()?
+        .join("");
+    let output = format!("\\begin{{verbatim}} {yaml} \\end{{verbatim}}");
     section_1.push(output.as_str());
     doc.push(section_1);
 
-    let mut section_2 = Section::new("Rounding analysis");
-    let output = format!("Expression: ${}$", visit(expr));
-    section_2.push(output.as_str());
-    doc.push(section_2);
+    for (index, (analysis, config)) in analyses.iter().zip(configs).enumerate() {
+        // A single unnamed formula does not need a title
+        let title = if configs.len() > 1 || config.name.is_some() {
+            format!("Rounding analysis: {}", escape(&config.label(index)))
+        } else {
+            String::from("Rounding analysis")
+        };
+        let mut section_2 = Section::new(&title);
+        let output = format!("Expression: ${}$", visit(&analysis.ast));
+        section_2.push(output.as_str());
+        if let Some(decimals) = analysis.decimals {
+            section_2.push(format!("Decimals of the result: {decimals}").as_str());
+        }
+
+        if !analysis.values.is_empty() {
+            section_2.push(values_table(&analysis.values).as_str());
+        }
+
+        if !analysis.unresolved.is_empty() {
+            section_2.push(
+                "The operations marked with $\\updownarrow$ depend on the following assumptions:",
+            );
+            let mut list = List::new(ListKind::Itemize);
+            for expr in &analysis.unresolved {
+                list.push(format!("Is \\verb|{expr}| greater than 1?"));
+            }
+            section_2.push(list);
+        }
+
+        if !analysis.undetermined.is_empty() {
+            section_2.push(
+                "The monotonicity of the following operations cannot be determined from the ranges:",
+            );
+            let mut list = List::new(ListKind::Itemize);
+            for explanation in &analysis.undetermined {
+                list.push(escape(explanation));
+            }
+            section_2.push(list);
+        }
+
+        if !analysis.underflows.is_empty() {
+            section_2.push("The following subtractions can underflow under the declared ranges:");
+            let mut list = List::new(ListKind::Itemize);
+            for underflow in &analysis.underflows {
+                list.push(escape(underflow));
+            }
+            section_2.push(list);
+        }
+
+        if !analysis.precision_losses.is_empty() {
+            section_2.push("The following divisions are multiplied, amplifying their truncation:");
+            let mut list = List::new(ListKind::Itemize);
+            for loss in &analysis.precision_losses {
+                list.push(escape(&loss.to_string()));
+            }
+            section_2.push(list);
+        }
+
+        if !analysis.magnitudes.is_empty() {
+            section_2.push(magnitudes_table(&analysis.magnitudes).as_str());
+        }
+
+        if let Some(total_error) = &analysis.total_error {
+            section_2.push(error_bounds_table(&analysis.error_bounds, total_error).as_str());
+        }
+
+        if !analysis.scale_mismatches.is_empty() {
+            section_2.push("The following operations combine values with different decimals:");
+            let mut list = List::new(ListKind::Itemize);
+            for mismatch in &analysis.scale_mismatches {
+                list.push(escape(&mismatch.to_string()));
+            }
+            section_2.push(list);
+        }
+
+        if !analysis.conflicts.is_empty() {
+            section_2.push("The following values are required to round in opposite directions:");
+            let mut list = List::new(ListKind::Itemize);
+            for conflict in &analysis.conflicts {
+                list.push(escape(&conflict.to_string()));
+            }
+            section_2.push(list);
+        }
+
+        if !analysis.checks.is_empty() {
+            section_2.push("Verification of the implemented rounding:");
+            let mut list = List::new(ListKind::Itemize);
+            for check in &analysis.checks {
+                list.push(escape(&check.to_string()));
+            }
+            section_2.push(list);
+        }
+        doc.push(section_2);
+    }
 
     let mut section_3 = Section::new("roundme");
     let text = format!(
@@ -117,8 +338,7 @@ pub fn generate(expr: &Expr, config: &FormulaConfig) -> Result<String> {
 ///
 pub fn write(rendered: &String) -> io::Result<()> {
     // Open the file in write mode, which will create or truncate it
-    let path = PathBuf::from_str("report.tex")
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+    let path = PathBuf::from_str("report.tex").map_err(|e| io::Error::other(e.to_string()))?;
 
     let mut f = File::create(path.as_path())?;
     // Write the rendered string to the file
@@ -135,8 +355,7 @@ pub fn write(rendered: &String) -> io::Result<()> {
 
     // Check if latexmk command was successful
     if !exit_status.success() {
-        return Err(io::Error::new(
-            io::ErrorKind::Other,
+        return Err(io::Error::other(
             "latexmk command failed. Is it installed? (https://mg.readthedocs.io/latexmk.html)",
         )); // Return an error if not successful
     };
@@ -146,8 +365,7 @@ pub fn write(rendered: &String) -> io::Result<()> {
     if exit_status.success() {
         Ok(()) // Return Ok if successful
     } else {
-        Err(io::Error::new(
-            io::ErrorKind::Other,
+        Err(io::Error::other(
             "latexmk command failed. Is it installed? (https://mg.readthedocs.io/latexmk.html)",
         )) // Return an error if not successful
     }
@@ -157,15 +375,152 @@ pub fn write(rendered: &String) -> io::Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::analyzer::ast::Condition;
+    use num_rational::BigRational;
+    use std::cell::RefCell;
 
     #[test]
     fn test_visit() {
         let expr = Expr::Op(
             Box::new(Expr::Id(String::from("a"))),
             Opcode::Add,
-            Box::new(Expr::Number(3)),
+            Box::new(Expr::Number(3.into())),
         );
         let result = visit(&expr);
         assert_eq!(result, "({a} + {3})");
     }
+
+    #[test]
+    fn test_visit_number() {
+        let expr = Expr::Number("1_000".parse().unwrap());
+        assert_eq!(visit(&expr), "1000");
+    }
+
+    #[test]
+    fn test_visit_id() {
+        let expr = Expr::Id(String::from("pool.token0_reserve[i]"));
+        assert_eq!(visit(&expr), "pool.token0\\_reserve[i]");
+    }
+
+    #[test]
+    fn test_visit_call() {
+        let args = vec![Expr::Id(String::from("a")), Expr::Id(String::from("b"))];
+        let expr = Expr::Call(
+            String::from("FixedPoint.mulDown"),
+            args,
+            RefCell::new(Rounding::Up),
+        );
+        assert_eq!(
+            visit(&expr),
+            "\\mathrm{FixedPoint.mulDown}_{\\uparrow}({a}, {b})"
+        );
+    }
+
+    #[test]
+    fn test_visit_monotone_functions() {
+        let call = |name: &str, rounding| {
+            let args = vec![Expr::Id(String::from("a"))];
+            Expr::Call(String::from(name), args, RefCell::new(rounding))
+        };
+        assert_eq!(visit(&call("sqrt", Rounding::Init)), "\\sqrt{{a}}");
+        assert_eq!(
+            visit(&call("LogExpMath.ln", Rounding::Down)),
+            "{\\ln({a})}_{\\downarrow}"
+        );
+        assert_eq!(visit(&call("exp", Rounding::Init)), "e^{{a}}");
+        // The WAD functions scale their argument, they are not e^x
+        assert_eq!(
+            visit(&call("expWad", Rounding::Init)),
+            "\\mathrm{expWad}_{}({a})"
+        );
+        assert_eq!(visit(&call("abs", Rounding::Init)), "\\left|{a}\\right|");
+    }
+
+    #[test]
+    fn test_visit_cond() {
+        let condition = Condition {
+            left: Box::new(Expr::Id(String::from("x"))),
+            comparison: Comparison::Ge,
+            right: Box::new(Expr::Id(String::from("y"))),
+        };
+        let expr = Expr::Cond(
+            Box::new(condition),
+            Box::new(Expr::Id(String::from("x"))),
+            Box::new(Expr::Number(0.into())),
+        );
+        assert_eq!(
+            visit(&expr),
+            "\\left\\{\\begin{array}{ll} {x} & \\text{if } {x} \\geq {y} \\\\ {0} & \\text{otherwise} \\end{array}\\right."
+        );
+    }
+
+    #[test]
+    fn test_visit_neg() {
+        let expr = Expr::Op(
+            Box::new(Expr::Neg(Box::new(Expr::Id(String::from("a"))))),
+            Opcode::Add,
+            Box::new(Expr::Number((-3).into())),
+        );
+        assert_eq!(visit(&expr), "({-{a}} + {-3})");
+    }
+
+    #[test]
+    fn test_values_table() {
+        let values = vec![
+            (String::from("a_b"), ValueRounding::Required(Rounding::Up)),
+            (String::from("c"), ValueRounding::Conflict),
+        ];
+        assert_eq!(
+            values_table(&values),
+            "\\begin{tabular}{ll}\n\\hline\nValue & Rounding \\\\\n\\hline\na\\_b & up \\\\\nc & conflict \\\\\n\\hline\n\\end{tabular}"
+        );
+    }
+
+    #[test]
+    fn test_magnitudes_table() {
+        let magnitudes = [
+            Magnitude {
+                expression: String::from("a * b"),
+                bound: Some(BigRational::from_integer(255.into())),
+            },
+            Magnitude {
+                expression: String::from("exp(a)"),
+                bound: None,
+            },
+        ];
+        assert_eq!(
+            magnitudes_table(&magnitudes),
+            "\\begin{tabular}{lll}\n\\hline\nOperation & Magnitude & Overflow \\\\\n\\hline\na * b & $< 2^{8}$ &  \\\\\nexp(a) & unbounded & yes \\\\\n\\hline\n\\end{tabular}"
+        );
+    }
+
+    #[test]
+    fn test_error_bounds_table() {
+        let bounds = [ErrorBound {
+            expression: String::from("a / b"),
+            absolute: Some(BigRational::from_integer(1.into())),
+            relative: None,
+        }];
+        let total = ErrorBound {
+            expression: String::from("exp(a / b)"),
+            absolute: None,
+            relative: None,
+        };
+        assert_eq!(
+            error_bounds_table(&bounds, &total),
+            "\\begin{tabular}{lll}\n\\hline\nOperation & Absolute error & Relative error \\\\\n\\hline\na / b & $\\pm$1 &  \\\\\n\\hline\nTotal & unbounded &  \\\\\n\\hline\n\\end{tabular}"
+        );
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("token0_reserve"), "token0\\_reserve");
+        assert_eq!(escape("outGivenIn"), "outGivenIn");
+        assert_eq!(escape("a *↓ b"), "a *$\\downarrow$ b");
+        assert_eq!(
+            escape("a > amount ? a : b, losing up to |d|"),
+            "a \\textgreater{} amount ? a : b, losing up to \\textbar{}d\\textbar{}"
+        );
+        assert_eq!(escape("a < b"), "a \\textless{} b");
+    }
 }